@@ -0,0 +1,13 @@
+use xcap::{available_backends, current_backend};
+
+fn main() {
+    for backend in available_backends() {
+        println!(
+            "Backend:\n name: {}\n capabilities: {:?}\n",
+            backend.name(),
+            backend.capabilities()
+        );
+    }
+
+    println!("current_backend(): {:?}", current_backend());
+}
@@ -0,0 +1,39 @@
+use std::{thread, time::Duration};
+use xcap::Monitor;
+
+fn main() {
+    let monitor = Monitor::from_point(100, 100).unwrap();
+
+    let (video_recorder, receiver) = monitor.video_recorder().unwrap();
+    video_recorder.set_fps(10);
+
+    thread::spawn(move || {
+        for frame in receiver {
+            let frame = match frame {
+                Ok(frame) => frame,
+                Err(err) => {
+                    println!("recording failed: {}", err);
+                    break;
+                }
+            };
+            println!(
+                "frame: {:?} {:?} {}",
+                frame.timestamp,
+                (frame.width, frame.height),
+                frame.data.len()
+            );
+        }
+    });
+
+    println!("start");
+    video_recorder.start().unwrap();
+    thread::sleep(Duration::from_secs(2));
+    println!("stop");
+    video_recorder.stop().unwrap();
+    thread::sleep(Duration::from_secs(2));
+    println!("start");
+    video_recorder.start().unwrap();
+    thread::sleep(Duration::from_secs(2));
+    println!("stop");
+    video_recorder.stop().unwrap();
+}
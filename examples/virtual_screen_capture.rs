@@ -0,0 +1,28 @@
+use fs_extra::dir;
+use std::time::Instant;
+use xcap::VirtualScreen;
+
+fn main() {
+    let start = Instant::now();
+    let virtual_screen = VirtualScreen::new().unwrap();
+
+    dir::create_all("target/virtual_screen", true).unwrap();
+
+    println!(
+        "VirtualScreen: {:?} {} monitors",
+        (
+            virtual_screen.x(),
+            virtual_screen.y(),
+            virtual_screen.width(),
+            virtual_screen.height()
+        ),
+        virtual_screen.monitors().len()
+    );
+
+    let image = virtual_screen.capture_image().unwrap();
+    image
+        .save("target/virtual_screen/virtual-screen.png")
+        .unwrap();
+
+    println!("运行耗时: {:?}", start.elapsed());
+}
@@ -0,0 +1,43 @@
+#[cfg(target_os = "linux")]
+fn main() {
+    use std::{thread, time::Duration};
+    use xcap::{Window, WindowRecorderEvent};
+
+    let windows = Window::all().unwrap();
+    let window = windows
+        .iter()
+        .find(|window| !window.is_minimized())
+        .unwrap();
+
+    println!("Window: {:?}", window.title());
+
+    let (video_recorder, receiver) = window.video_recorder().unwrap();
+    video_recorder.set_fps(10);
+
+    thread::spawn(move || {
+        for event in receiver {
+            match event {
+                WindowRecorderEvent::Frame(frame) => {
+                    println!(
+                        "frame: {:?} {:?}",
+                        frame.timestamp,
+                        (frame.width, frame.height)
+                    )
+                }
+                WindowRecorderEvent::Resized { width, height } => {
+                    println!("resized: {:?}", (width, height))
+                }
+                WindowRecorderEvent::Closed(err) => println!("closed: {}", err),
+            }
+        }
+    });
+
+    video_recorder.start().unwrap();
+    thread::sleep(Duration::from_secs(5));
+    video_recorder.stop().unwrap();
+}
+
+#[cfg(not(target_os = "linux"))]
+fn main() {
+    println!("Window recording is only supported on Linux");
+}
@@ -0,0 +1,185 @@
+use std::sync::Mutex;
+
+use crate::error::{XCapError, XCapResult};
+
+/// The mechanism used to capture the screen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Backend {
+    /// The X11 protocol, on X11 sessions.
+    X11,
+    /// The `org.gnome.Shell.Screenshot` D-Bus interface of GNOME Shell.
+    GnomeShell,
+    /// The `org.kde.KWin.ScreenShot2` D-Bus interface of KDE Plasma.
+    KWin,
+    /// The `org.freedesktop.portal.Screenshot` interface of xdg-desktop-portal.
+    FreedesktopPortal,
+    /// The `ext_image_copy_capture_v1` and `ext_foreign_toplevel_list_v1` Wayland protocols,
+    /// which also capture individual windows.
+    ExtImageCopyCapture,
+    /// The `zwlr_screencopy_manager_v1` Wayland protocol of wlroots based compositors
+    /// such as Sway, Hyprland and river.
+    WlrScreencopy,
+    /// The `org.freedesktop.portal.ScreenCast` interface of xdg-desktop-portal,
+    /// with frames received from PipeWire. Needs the `pipewire` feature.
+    PipeWire,
+    /// The GDI API on Windows.
+    Gdi,
+    /// The Core Graphics API on macOS.
+    CoreGraphics,
+}
+
+/// What a backend can provide.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Capabilities {
+    /// `Window::all()` lists the windows of the session.
+    pub window_enumeration: bool,
+    /// `Window::capture_image()` works.
+    pub window_capture: bool,
+    /// `Monitor::capture_region()` works.
+    pub region_capture: bool,
+    /// The mouse cursor can be drawn onto captured images.
+    pub cursor: bool,
+    /// Capturing never shows a dialog to the user.
+    /// Backends without it are tried last, after every non-interactive backend failed.
+    pub non_interactive: bool,
+    /// `Monitor::video_recorder()` works.
+    pub video_recording: bool,
+}
+
+impl Backend {
+    /// The backend name.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Backend::X11 => "X11",
+            Backend::GnomeShell => "GNOME Shell",
+            Backend::KWin => "KWin",
+            Backend::FreedesktopPortal => "xdg-desktop-portal",
+            Backend::ExtImageCopyCapture => "ext-image-copy-capture",
+            Backend::WlrScreencopy => "wlr-screencopy",
+            Backend::PipeWire => "PipeWire",
+            Backend::Gdi => "GDI",
+            Backend::CoreGraphics => "Core Graphics",
+        }
+    }
+
+    /// What xcap supports with this backend.
+    pub fn capabilities(&self) -> Capabilities {
+        match self {
+            Backend::X11 => Capabilities {
+                window_enumeration: true,
+                window_capture: true,
+                region_capture: true,
+                cursor: true,
+                non_interactive: true,
+                video_recording: true,
+            },
+            Backend::GnomeShell => Capabilities {
+                region_capture: true,
+                non_interactive: true,
+                ..Default::default()
+            },
+            // KWin 不提供窗口列表，窗口来自 XWayland 或 ext-foreign-toplevel-list
+            Backend::KWin => Capabilities {
+                window_capture: true,
+                region_capture: true,
+                cursor: true,
+                non_interactive: true,
+                ..Default::default()
+            },
+            // 门户可能弹出授权对话框
+            Backend::FreedesktopPortal => Capabilities {
+                region_capture: true,
+                ..Default::default()
+            },
+            // 窗口没有位置与层级信息，录制窗口仍然只支持 X11
+            Backend::ExtImageCopyCapture => Capabilities {
+                window_enumeration: true,
+                window_capture: true,
+                region_capture: true,
+                cursor: true,
+                non_interactive: true,
+                video_recording: true,
+            },
+            Backend::WlrScreencopy => Capabilities {
+                region_capture: true,
+                cursor: true,
+                non_interactive: true,
+                video_recording: true,
+                ..Default::default()
+            },
+            // 只在建立会话时弹出一次对话框，之后从视频流中读取
+            Backend::PipeWire => Capabilities {
+                region_capture: true,
+                video_recording: true,
+                ..Default::default()
+            },
+            Backend::Gdi | Backend::CoreGraphics => Capabilities {
+                window_enumeration: true,
+                window_capture: true,
+                region_capture: true,
+                cursor: false,
+                non_interactive: true,
+                video_recording: true,
+            },
+        }
+    }
+}
+
+static SELECTED_BACKEND: Mutex<Option<Backend>> = Mutex::new(None);
+
+/// The backends usable in the current session, in the order they are tried.
+pub fn available_backends() -> Vec<Backend> {
+    #[cfg(target_os = "linux")]
+    {
+        crate::platform::backend::available_backends().to_vec()
+    }
+
+    #[cfg(target_os = "windows")]
+    {
+        vec![Backend::Gdi]
+    }
+
+    #[cfg(target_os = "macos")]
+    {
+        vec![Backend::CoreGraphics]
+    }
+}
+
+/// Use `backend` for all following captures, `None` restores the automatic selection.
+pub fn set_backend(backend: Option<Backend>) -> XCapResult<()> {
+    if let Some(backend) = backend {
+        if !available_backends().contains(&backend) {
+            return Err(XCapError::Unsupported {
+                feature: "Capture",
+                backend: backend.name(),
+            });
+        }
+    }
+
+    let mut selected_backend = SELECTED_BACKEND
+        .lock()
+        .map_err(|_| XCapError::new("Get backend lock failed"))?;
+    *selected_backend = backend;
+
+    Ok(())
+}
+
+/// The backend set with `set_backend`, if any.
+pub fn selected_backend() -> Option<Backend> {
+    SELECTED_BACKEND
+        .lock()
+        .map(|selected_backend| *selected_backend)
+        .unwrap_or(None)
+}
+
+/// The backend that the next capture uses.
+pub fn current_backend() -> XCapResult<Backend> {
+    if let Some(backend) = selected_backend() {
+        return Ok(backend);
+    }
+
+    available_backends()
+        .first()
+        .copied()
+        .ok_or_else(|| XCapError::NoDisplay(String::from("No capture backend available")))
+}
@@ -0,0 +1,41 @@
+use crate::{backend::Backend, platform::capture::last_capture_method};
+
+/// Options for `Monitor::capture_image_with_options`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CaptureOptions {
+    /// Draw the mouse cursor onto the captured image.
+    pub include_cursor: bool,
+    /// Capture with this backend instead of the one from `set_backend`
+    /// or the automatic selection.
+    pub backend: Option<Backend>,
+}
+
+/// Which part of a window `Window::capture_image_with_mode` captures.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum WindowCaptureMode {
+    /// The client window as it is, the same as `Window::capture_image`.
+    #[default]
+    Client,
+    /// The client window with the window manager decorations around it.
+    Frame,
+    /// Like `Frame`, without the invisible shadows drawn by client-side decorations.
+    VisibleFrame,
+}
+
+/// How the X server sent the pixels of a capture, see `last_x11_capture_method`
+/// and `CaptureSession::last_capture_method`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum X11CaptureMethod {
+    /// Through a MIT-SHM shared memory segment.
+    Shm,
+    /// Through a plain `GetImage` request over the socket, used on remote displays
+    /// or when the X server refuses shared memory.
+    GetImage,
+}
+
+/// How the X server sent the pixels of the last X11 capture made through `Monitor`, `Window`
+/// or `VirtualScreen`, `None` before the first one.
+/// Captures of a `CaptureSession` are reported by `CaptureSession::last_capture_method`.
+pub fn last_x11_capture_method() -> Option<X11CaptureMethod> {
+    last_capture_method()
+}
@@ -0,0 +1,128 @@
+use image::RgbaImage;
+
+use crate::{
+    capture_options::{WindowCaptureMode, X11CaptureMethod},
+    error::XCapResult,
+    frame_buffer::FrameBuffer,
+    platform::impl_capture_session::ImplCaptureSession,
+    rect::Rect,
+    Monitor, Window,
+};
+
+/// A connection to the X server kept open between captures.
+///
+/// `Monitor::all()` and `Window::all()` connect to the X server on every call, the capture
+/// methods share one connection for the whole process. A session connects once and reuses
+/// its own connection, interned atoms and MIT-SHM segment for listing and capturing,
+/// which are released when it is dropped.
+/// Only X11 monitors and windows can be captured, including XWayland ones.
+#[derive(Debug)]
+pub struct CaptureSession {
+    pub(crate) impl_capture_session: ImplCaptureSession,
+}
+
+impl CaptureSession {
+    /// Connect to the X server of the `DISPLAY` environment variable.
+    pub fn new() -> XCapResult<CaptureSession> {
+        let impl_capture_session = ImplCaptureSession::new()?;
+
+        Ok(CaptureSession {
+            impl_capture_session,
+        })
+    }
+
+    /// All monitors, like `Monitor::all()`.
+    pub fn monitors(&self) -> XCapResult<Vec<Monitor>> {
+        let monitors = self
+            .impl_capture_session
+            .monitors()?
+            .into_iter()
+            .map(Monitor::new)
+            .collect();
+
+        Ok(monitors)
+    }
+
+    /// All windows, like `Window::all()`.
+    pub fn windows(&self) -> XCapResult<Vec<Window>> {
+        let windows = self
+            .impl_capture_session
+            .windows()?
+            .into_iter()
+            .map(Window::new)
+            .collect();
+
+        Ok(windows)
+    }
+
+    /// How the X server sent the pixels of the last capture of this session,
+    /// `None` before the first capture.
+    pub fn last_capture_method(&self) -> Option<X11CaptureMethod> {
+        self.impl_capture_session.last_capture_method()
+    }
+
+    /// Capture the whole monitor.
+    pub fn capture(&self, monitor: &Monitor) -> XCapResult<RgbaImage> {
+        self.capture_region(monitor, 0, 0, monitor.width(), monitor.height())
+    }
+
+    /// Capture a region of the monitor, relative to its top left corner.
+    pub fn capture_region(
+        &self,
+        monitor: &Monitor,
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> XCapResult<RgbaImage> {
+        let mut frame_buffer = FrameBuffer::new();
+        self.capture_region_into(monitor, x, y, width, height, &mut frame_buffer)?;
+
+        frame_buffer.into_rgba_image()
+    }
+
+    /// Capture the whole monitor into `frame_buffer`, reusing its allocation.
+    pub fn capture_into(
+        &self,
+        monitor: &Monitor,
+        frame_buffer: &mut FrameBuffer,
+    ) -> XCapResult<()> {
+        self.capture_region_into(
+            monitor,
+            0,
+            0,
+            monitor.width(),
+            monitor.height(),
+            frame_buffer,
+        )
+    }
+
+    /// Capture a region of the monitor into `frame_buffer`, reusing its allocation.
+    pub fn capture_region_into(
+        &self,
+        monitor: &Monitor,
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+        frame_buffer: &mut FrameBuffer,
+    ) -> XCapResult<()> {
+        monitor.check_region(x, y, width, height)?;
+
+        self.impl_capture_session.capture_region_into(
+            &monitor.impl_monitor,
+            Rect::new(x as i32, y as i32, width, height),
+            frame_buffer,
+        )
+    }
+
+    /// Capture the window, like `Window::capture_image_with_mode()`.
+    pub fn capture_window(
+        &self,
+        window: &Window,
+        mode: WindowCaptureMode,
+    ) -> XCapResult<RgbaImage> {
+        self.impl_capture_session
+            .capture_window(&window.impl_window, mode)
+    }
+}
@@ -0,0 +1,50 @@
+use image::RgbaImage;
+
+use crate::{error::XCapResult, platform::impl_cursor::ImplCursor};
+
+/// The mouse cursor image and position at the time it was fetched.
+#[derive(Debug, Clone)]
+pub struct Cursor {
+    pub(crate) impl_cursor: ImplCursor,
+}
+
+impl Cursor {
+    pub(crate) fn new(impl_cursor: ImplCursor) -> Cursor {
+        Cursor { impl_cursor }
+    }
+}
+
+impl Cursor {
+    pub fn current() -> XCapResult<Cursor> {
+        let impl_cursor = ImplCursor::new()?;
+
+        Ok(Cursor::new(impl_cursor))
+    }
+}
+
+impl Cursor {
+    /// The cursor x coordinate, in the same space as `Monitor::x()`.
+    pub fn x(&self) -> i32 {
+        self.impl_cursor.x
+    }
+    /// The cursor y coordinate, in the same space as `Monitor::y()`.
+    pub fn y(&self) -> i32 {
+        self.impl_cursor.y
+    }
+    /// The hotspot x offset inside the cursor image, in pixels.
+    pub fn hotspot_x(&self) -> u32 {
+        self.impl_cursor.hotspot_x
+    }
+    /// The hotspot y offset inside the cursor image, in pixels.
+    pub fn hotspot_y(&self) -> u32 {
+        self.impl_cursor.hotspot_y
+    }
+    /// Changes whenever the cursor image changes.
+    pub fn serial(&self) -> u32 {
+        self.impl_cursor.serial
+    }
+    /// The cursor image, with straight (not premultiplied) alpha.
+    pub fn image(&self) -> &RgbaImage {
+        &self.impl_cursor.image
+    }
+}
@@ -0,0 +1,57 @@
+use image::RgbaImage;
+
+use crate::{error::XCapResult, platform::impl_damage_tracker::ImplDamageTracker, Monitor, Window};
+
+/// A changed area, relative to the top-left corner of the tracked monitor or window,
+/// in pixels of the captured image.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DamageRect {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Tracks which areas of a monitor or window changed between calls,
+/// and keeps an up to date image by re-capturing only those areas.
+#[derive(Debug)]
+pub struct DamageTracker {
+    pub(crate) impl_damage_tracker: ImplDamageTracker,
+}
+
+impl DamageTracker {
+    pub(crate) fn new(impl_damage_tracker: ImplDamageTracker) -> DamageTracker {
+        DamageTracker {
+            impl_damage_tracker,
+        }
+    }
+}
+
+impl DamageTracker {
+    pub fn from_monitor(monitor: &Monitor) -> XCapResult<DamageTracker> {
+        let impl_damage_tracker = ImplDamageTracker::from_monitor(&monitor.impl_monitor)?;
+
+        Ok(DamageTracker::new(impl_damage_tracker))
+    }
+
+    pub fn from_window(window: &Window) -> XCapResult<DamageTracker> {
+        let impl_damage_tracker = ImplDamageTracker::from_window(&window.impl_window)?;
+
+        Ok(DamageTracker::new(impl_damage_tracker))
+    }
+}
+
+impl DamageTracker {
+    /// The areas that changed since the previous call.
+    /// It does not affect which areas `capture_image` re-captures.
+    pub fn damaged_regions(&mut self) -> XCapResult<Vec<DamageRect>> {
+        self.impl_damage_tracker.damaged_regions()
+    }
+
+    /// The current image. The first call captures everything, later calls only re-capture
+    /// the areas that changed since the previous call, including those already returned
+    /// by `damaged_regions`.
+    pub fn capture_image(&mut self) -> XCapResult<&RgbaImage> {
+        self.impl_damage_tracker.capture_image()
+    }
+}
@@ -1,16 +1,49 @@
 use thiserror::Error;
 
+use crate::rect::Rect;
+
 #[derive(Debug, Error)]
 pub enum XCapError {
     #[error("{0}")]
     Error(String),
 
+    /// The system or the user refused access to the screen contents.
+    #[error("Permission denied: {0}")]
+    PermissionDenied(String),
+    /// The user dismissed the capture dialog.
+    #[error("Capture cancelled by the user")]
+    UserCancelled,
+    /// The feature is not available with the current capture backend.
+    #[error("{feature} is not supported on {backend}")]
+    Unsupported {
+        feature: &'static str,
+        backend: &'static str,
+    },
+    /// The window with this id was closed.
+    #[error("Window {0} is gone")]
+    WindowGone(u32),
+    /// The monitor with this id was disconnected.
+    #[error("Monitor {0} is gone")]
+    MonitorGone(u32),
+    /// No answer arrived in time, e.g. from a capture dialog.
+    #[error("Timed out: {0}")]
+    Timeout(String),
+    /// The requested region is not inside the captured area.
+    #[error("Region {region:?} out of bounds {bounds:?}")]
+    InvalidRegion { region: Rect, bounds: Rect },
+    /// No display server could be connected to.
+    #[error("No display available: {0}")]
+    NoDisplay(String),
+
+    #[cfg(target_os = "linux")]
+    #[error(transparent)]
+    XcbError(xcb::Error),
     #[cfg(target_os = "linux")]
     #[error(transparent)]
-    XcbError(#[from] xcb::Error),
+    XcbConnError(xcb::ConnError),
     #[cfg(target_os = "linux")]
     #[error(transparent)]
-    XcbConnError(#[from] xcb::ConnError),
+    XcbProtocolError(#[from] xcb::ProtocolError),
     #[cfg(target_os = "linux")]
     #[error(transparent)]
     ImageImageError(#[from] image::ImageError),
@@ -19,13 +52,28 @@ pub enum XCapError {
     StdStrUtf8Error(#[from] std::str::Utf8Error),
     #[cfg(target_os = "linux")]
     #[error(transparent)]
-    DbusError(#[from] dbus::Error),
+    DbusError(dbus::Error),
     #[cfg(target_os = "linux")]
     #[error(transparent)]
     StdIOError(#[from] std::io::Error),
     #[cfg(target_os = "linux")]
     #[error(transparent)]
     StdTimeSystemTimeError(#[from] std::time::SystemTimeError),
+    #[cfg(target_os = "linux")]
+    #[error(transparent)]
+    WaylandConnectError(wayland_client::ConnectError),
+    #[cfg(target_os = "linux")]
+    #[error(transparent)]
+    WaylandGlobalError(#[from] wayland_client::globals::GlobalError),
+    #[cfg(target_os = "linux")]
+    #[error(transparent)]
+    WaylandBindError(#[from] wayland_client::globals::BindError),
+    #[cfg(target_os = "linux")]
+    #[error(transparent)]
+    WaylandDispatchError(#[from] wayland_client::DispatchError),
+    #[cfg(all(target_os = "linux", feature = "pipewire"))]
+    #[error(transparent)]
+    PipewireError(#[from] pipewire::Error),
 
     #[cfg(target_os = "macos")]
     #[error("CoreGraphicsDisplayCGError {0}")]
@@ -45,6 +93,62 @@ impl XCapError {
     }
 }
 
+#[cfg(target_os = "linux")]
+impl From<xcb::ConnError> for XCapError {
+    fn from(value: xcb::ConnError) -> Self {
+        match value {
+            // 无法连接或 DISPLAY 无效，都视为没有可用的显示
+            xcb::ConnError::Connection
+            | xcb::ConnError::ClosedParseErr
+            | xcb::ConnError::ClosedInvalidScreen => XCapError::NoDisplay(value.to_string()),
+            _ => XCapError::XcbConnError(value),
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl From<xcb::Error> for XCapError {
+    fn from(value: xcb::Error) -> Self {
+        match value {
+            xcb::Error::Connection(err) => match err.into() {
+                XCapError::XcbConnError(err) => XCapError::XcbError(xcb::Error::Connection(err)),
+                err => err,
+            },
+            xcb::Error::Protocol(_) => XCapError::XcbError(value),
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl From<dbus::Error> for XCapError {
+    fn from(value: dbus::Error) -> Self {
+        let message = value.message().unwrap_or_default().to_string();
+
+        match value.name() {
+            Some("org.freedesktop.DBus.Error.AccessDenied")
+            | Some("org.freedesktop.DBus.Error.AuthFailed")
+            | Some("org.kde.KWin.ScreenShot2.Error.NoAuthorized") => {
+                XCapError::PermissionDenied(message)
+            }
+            Some("org.kde.KWin.ScreenShot2.Error.Cancelled") => XCapError::UserCancelled,
+            Some("org.freedesktop.DBus.Error.NoReply")
+            | Some("org.freedesktop.DBus.Error.Timeout")
+            | Some("org.freedesktop.DBus.Error.TimedOut") => XCapError::Timeout(message),
+            _ => XCapError::DbusError(value),
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl From<wayland_client::ConnectError> for XCapError {
+    fn from(value: wayland_client::ConnectError) -> Self {
+        match value {
+            wayland_client::ConnectError::NoCompositor => XCapError::NoDisplay(value.to_string()),
+            _ => XCapError::WaylandConnectError(value),
+        }
+    }
+}
+
 #[cfg(target_os = "macos")]
 impl From<core_graphics::display::CGError> for XCapError {
     fn from(value: core_graphics::display::CGError) -> Self {
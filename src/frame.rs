@@ -0,0 +1,270 @@
+use image::RgbaImage;
+use std::time::Duration;
+
+use crate::error::{XCapError, XCapResult};
+#[cfg(target_os = "linux")]
+use crate::rect::Rect;
+
+/// The memory layout of the pixels in a `Frame`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PixelFormat {
+    /// 4 bytes per pixel: red, green, blue, alpha.
+    Rgba8,
+    /// 4 bytes per pixel: red, green, blue and an undefined byte.
+    Rgbx8,
+    /// 4 bytes per pixel: blue, green, red, alpha.
+    Bgra8,
+    /// 4 bytes per pixel: blue, green, red and an undefined byte.
+    Bgrx8,
+    /// A little-endian `u16` per pixel, red in bits 11..16, green in 5..11 and blue in 0..5.
+    Rgb565,
+    /// A little-endian `u32` per pixel, blue in bits 0..10, green in 10..20,
+    /// red in 20..30 and alpha in 30..32.
+    Rgb10A2,
+}
+
+impl PixelFormat {
+    pub fn bytes_per_pixel(&self) -> u32 {
+        match self {
+            PixelFormat::Rgb565 => 2,
+            PixelFormat::Rgba8
+            | PixelFormat::Rgbx8
+            | PixelFormat::Bgra8
+            | PixelFormat::Bgrx8
+            | PixelFormat::Rgb10A2 => 4,
+        }
+    }
+
+    fn to_rgba(self, pixel: &[u8]) -> [u8; 4] {
+        match self {
+            PixelFormat::Rgba8 => [pixel[0], pixel[1], pixel[2], pixel[3]],
+            PixelFormat::Rgbx8 => [pixel[0], pixel[1], pixel[2], 255],
+            PixelFormat::Bgra8 => [pixel[2], pixel[1], pixel[0], pixel[3]],
+            PixelFormat::Bgrx8 => [pixel[2], pixel[1], pixel[0], 255],
+            PixelFormat::Rgb565 => {
+                let pixel = u16::from_le_bytes([pixel[0], pixel[1]]) as u32;
+                let r = (pixel >> 11) & 0x1f;
+                let g = (pixel >> 5) & 0x3f;
+                let b = pixel & 0x1f;
+
+                [
+                    (r * 255 / 31) as u8,
+                    (g * 255 / 63) as u8,
+                    (b * 255 / 31) as u8,
+                    255,
+                ]
+            }
+            PixelFormat::Rgb10A2 => {
+                let pixel = u32::from_le_bytes([pixel[0], pixel[1], pixel[2], pixel[3]]);
+
+                [
+                    (pixel >> 22) as u8,
+                    (pixel >> 12) as u8,
+                    (pixel >> 2) as u8,
+                    ((pixel >> 30) * 85) as u8,
+                ]
+            }
+        }
+    }
+}
+
+/// Captured pixels in the layout the platform produced them.
+///
+/// Rows are `stride` bytes apart, which may be more than `width` times the pixel size.
+/// Video recorder frames are always `PixelFormat::Rgba8`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Frame {
+    pub width: u32,
+    pub height: u32,
+    /// The number of bytes from the start of one row to the start of the next.
+    pub stride: u32,
+    pub pixel_format: PixelFormat,
+    /// Time elapsed since the recording was started, zero for single captures.
+    pub timestamp: Duration,
+    pub data: Vec<u8>,
+}
+
+impl Frame {
+    pub(crate) fn new(timestamp: Duration, rgba_image: RgbaImage) -> Frame {
+        Frame {
+            width: rgba_image.width(),
+            height: rgba_image.height(),
+            stride: rgba_image.width() * 4,
+            pixel_format: PixelFormat::Rgba8,
+            timestamp,
+            data: rgba_image.into_raw(),
+        }
+    }
+
+    /// Wrap a single capture that was already converted to RGBA8.
+    #[cfg(target_os = "linux")]
+    pub(crate) fn from_rgba_image(rgba_image: RgbaImage) -> Frame {
+        Frame::new(Duration::ZERO, rgba_image)
+    }
+
+    /// Wrap the pixels of a single capture, `data` must hold `height` rows of `stride` bytes.
+    pub(crate) fn from_raw(
+        width: u32,
+        height: u32,
+        stride: u32,
+        pixel_format: PixelFormat,
+        data: Vec<u8>,
+    ) -> XCapResult<Frame> {
+        let frame = Frame {
+            width,
+            height,
+            stride,
+            pixel_format,
+            timestamp: Duration::ZERO,
+            data,
+        };
+        frame.check_size()?;
+
+        Ok(frame)
+    }
+
+    fn check_size(&self) -> XCapResult<()> {
+        let row_size = self.width as usize * self.pixel_format.bytes_per_pixel() as usize;
+        let size = match self.height {
+            0 => 0,
+            height => (height as usize - 1) * self.stride as usize + row_size,
+        };
+
+        if (self.stride as usize) < row_size || self.data.len() < size {
+            return Err(XCapError::new(format!(
+                "Invalid {:?} frame: {}x{} with stride {} and {} bytes",
+                self.pixel_format,
+                self.width,
+                self.height,
+                self.stride,
+                self.data.len()
+            )));
+        }
+
+        Ok(())
+    }
+
+    /// Copy the pixels inside the region, clipped to the frame.
+    #[cfg(target_os = "linux")]
+    pub(crate) fn crop(self, x: u32, y: u32, width: u32, height: u32) -> XCapResult<Frame> {
+        if x == 0 && y == 0 && width == self.width && height == self.height {
+            return Ok(self);
+        }
+
+        self.check_size()?;
+
+        let bounds = Rect::new(0, 0, self.width, self.height);
+        let region = Rect::new(x as i32, y as i32, width, height);
+        let Some(region) = bounds.intersection(&region) else {
+            return Err(XCapError::InvalidRegion { region, bounds });
+        };
+
+        let bytes_per_pixel = self.pixel_format.bytes_per_pixel();
+        let row_size = (region.width * bytes_per_pixel) as usize;
+        let mut data = Vec::with_capacity(row_size * region.height as usize);
+        for y in region.y as u32..region.y as u32 + region.height {
+            let start = (y * self.stride + region.x as u32 * bytes_per_pixel) as usize;
+            data.extend_from_slice(&self.data[start..start + row_size]);
+        }
+
+        Ok(Frame {
+            width: region.width,
+            height: region.height,
+            stride: row_size as u32,
+            pixel_format: self.pixel_format,
+            timestamp: self.timestamp,
+            data,
+        })
+    }
+
+    /// One row of pixels, without the padding at the end.
+    /// `None` when `y` is not below `height` or `data` is too short for the row.
+    pub fn row(&self, y: u32) -> Option<&[u8]> {
+        if y >= self.height {
+            return None;
+        }
+
+        let start = y as usize * self.stride as usize;
+        let row_size = self.width as usize * self.pixel_format.bytes_per_pixel() as usize;
+
+        self.data.get(start..start + row_size)
+    }
+
+    /// Convert the pixels into an `RgbaImage`.
+    pub fn to_rgba_image(&self) -> XCapResult<RgbaImage> {
+        self.check_size()?;
+
+        let bytes_per_pixel = self.pixel_format.bytes_per_pixel() as usize;
+        let mut buffer = Vec::with_capacity((self.width * self.height * 4) as usize);
+        // check_size 之后每一行都存在
+        for row in (0..self.height).filter_map(|y| self.row(y)) {
+            if self.pixel_format == PixelFormat::Rgba8 {
+                buffer.extend_from_slice(row);
+                continue;
+            }
+
+            for pixel in row.chunks_exact(bytes_per_pixel) {
+                buffer.extend_from_slice(&self.pixel_format.to_rgba(pixel));
+            }
+        }
+
+        RgbaImage::from_raw(self.width, self.height, buffer)
+            .ok_or_else(|| XCapError::new("RgbaImage::from_raw failed"))
+    }
+
+    /// Convert into an `RgbaImage`, without copying RGBA8 pixels when the rows are not padded.
+    pub fn into_rgba_image(self) -> XCapResult<RgbaImage> {
+        if self.pixel_format != PixelFormat::Rgba8 || self.stride != self.width * 4 {
+            return self.to_rgba_image();
+        }
+
+        self.check_size()?;
+        let mut data = self.data;
+        data.truncate((self.width * self.height * 4) as usize);
+
+        RgbaImage::from_raw(self.width, self.height, data)
+            .ok_or_else(|| XCapError::new("RgbaImage::from_raw failed"))
+    }
+
+    /// Tightly packed BGRA8 pixels, for `capture_image_bgra_data`.
+    #[cfg(not(target_os = "windows"))]
+    pub(crate) fn into_bgra_data(self) -> XCapResult<Vec<u8>> {
+        if self.pixel_format == PixelFormat::Bgra8 && self.stride == self.width * 4 {
+            self.check_size()?;
+            let mut data = self.data;
+            data.truncate((self.width * self.height * 4) as usize);
+
+            return Ok(data);
+        }
+
+        let mut buffer = self.into_rgba_image()?.into_raw();
+        for pixel in buffer.chunks_exact_mut(4) {
+            pixel.swap(0, 2);
+        }
+
+        Ok(buffer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn row() {
+        let mut frame =
+            Frame::from_raw(2, 2, 12, PixelFormat::Bgra8, (0..24).collect::<Vec<u8>>()).unwrap();
+
+        assert_eq!(frame.row(0), Some([0, 1, 2, 3, 4, 5, 6, 7].as_slice()));
+        assert_eq!(
+            frame.row(1),
+            Some([12, 13, 14, 15, 16, 17, 18, 19].as_slice())
+        );
+        assert_eq!(frame.row(2), None);
+
+        // 字段是公开的，data 被截短后不会越界
+        frame.data.truncate(16);
+        assert_eq!(frame.row(1), None);
+        assert!(frame.to_rgba_image().is_err());
+    }
+}
@@ -0,0 +1,84 @@
+use image::RgbaImage;
+use std::time::Duration;
+
+use crate::{error::XCapResult, frame::Frame};
+
+/// A reusable RGBA8 pixel buffer for `Monitor::capture_into`.
+///
+/// On X11 the allocation is kept between captures and only grows when a capture is larger
+/// than any before it, other backends replace it with the newly captured image.
+/// Rows are not padded, `stride` is always `width * 4`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FrameBuffer {
+    frame: Frame,
+}
+
+impl Default for FrameBuffer {
+    fn default() -> FrameBuffer {
+        FrameBuffer {
+            frame: Frame::new(Duration::ZERO, RgbaImage::default()),
+        }
+    }
+}
+
+impl FrameBuffer {
+    /// An empty buffer, allocated by the first capture.
+    pub fn new() -> FrameBuffer {
+        FrameBuffer::default()
+    }
+
+    /// The pixel width of the last capture.
+    pub fn width(&self) -> u32 {
+        self.frame.width
+    }
+
+    /// The pixel height of the last capture.
+    pub fn height(&self) -> u32 {
+        self.frame.height
+    }
+
+    /// The number of bytes in a row, `width * 4`.
+    pub fn stride(&self) -> u32 {
+        self.frame.stride
+    }
+
+    /// The RGBA8 pixels, `stride * height` bytes.
+    pub fn data(&self) -> &[u8] {
+        &self.frame.data
+    }
+
+    /// One row of RGBA8 pixels, `None` when `y` is not below `height`.
+    pub fn row(&self, y: u32) -> Option<&[u8]> {
+        self.frame.row(y)
+    }
+
+    /// The pixels as a `PixelFormat::Rgba8` frame.
+    pub fn as_frame(&self) -> &Frame {
+        &self.frame
+    }
+
+    /// Copy the pixels into an `RgbaImage`.
+    pub fn to_rgba_image(&self) -> XCapResult<RgbaImage> {
+        self.frame.to_rgba_image()
+    }
+
+    /// Convert into an `RgbaImage` without copying.
+    pub fn into_rgba_image(self) -> XCapResult<RgbaImage> {
+        self.frame.into_rgba_image()
+    }
+
+    /// Set the size and return the pixels to write, keeping the allocation when it is large enough.
+    pub(crate) fn resize(&mut self, width: u32, height: u32) -> &mut [u8] {
+        self.frame.width = width;
+        self.frame.height = height;
+        self.frame.stride = width * 4;
+        self.frame.data.resize((width * height * 4) as usize, 0);
+
+        &mut self.frame.data
+    }
+
+    /// Take over the pixels of a captured image, replacing the allocation.
+    pub(crate) fn set_rgba_image(&mut self, rgba_image: RgbaImage) {
+        self.frame = Frame::new(Duration::ZERO, rgba_image);
+    }
+}
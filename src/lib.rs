@@ -1,5 +1,23 @@
+mod backend;
+#[cfg(target_os = "linux")]
+mod capture_options;
+#[cfg(target_os = "linux")]
+mod capture_session;
+#[cfg(target_os = "linux")]
+mod cursor;
+#[cfg(target_os = "linux")]
+mod damage_tracker;
 mod error;
+mod frame;
+#[cfg(target_os = "linux")]
+mod frame_buffer;
 mod monitor;
+#[cfg(all(target_os = "linux", feature = "pipewire"))]
+mod portal_session;
+mod process;
+mod rect;
+mod video_recorder;
+mod virtual_screen;
 mod window;
 
 #[cfg(target_os = "macos")]
@@ -16,8 +34,30 @@ mod platform;
 
 pub use image;
 
+pub use backend::{
+    available_backends, current_backend, selected_backend, set_backend, Backend, Capabilities,
+};
+#[cfg(target_os = "linux")]
+pub use capture_options::{
+    last_x11_capture_method, CaptureOptions, WindowCaptureMode, X11CaptureMethod,
+};
+#[cfg(target_os = "linux")]
+pub use capture_session::CaptureSession;
+#[cfg(target_os = "linux")]
+pub use cursor::Cursor;
+#[cfg(target_os = "linux")]
+pub use damage_tracker::{DamageRect, DamageTracker};
 pub use error::{XCapError, XCapResult};
+pub use frame::{Frame, PixelFormat};
+#[cfg(target_os = "linux")]
+pub use frame_buffer::FrameBuffer;
 pub use monitor::Monitor;
+#[cfg(all(target_os = "linux", feature = "pipewire"))]
+pub use portal_session::PortalSession;
+pub use process::Process;
+pub use rect::{FrameExtents, Rect};
+pub use video_recorder::{VideoRecorder, WindowRecorderEvent};
+pub use virtual_screen::{capture_all_monitors, VirtualScreen};
 pub use window::Window;
 
 #[cfg(target_os = "windows")]
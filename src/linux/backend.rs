@@ -0,0 +1,182 @@
+use dbus::blocking::Connection;
+use std::{env::var_os, sync::OnceLock, time::Duration};
+use wayland_client::{
+    globals::{registry_queue_init, GlobalListContents},
+    protocol::wl_registry::{self, WlRegistry},
+    Connection as WaylandConnection, Dispatch, QueueHandle,
+};
+
+use crate::{
+    backend::{current_backend, selected_backend, Backend, Capabilities},
+    error::{XCapError, XCapResult},
+};
+
+pub fn wayland_detect() -> bool {
+    let xdg_session_type = var_os("XDG_SESSION_TYPE")
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_string();
+
+    let wayland_display = var_os("WAYLAND_DISPLAY")
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_string();
+
+    xdg_session_type.eq("wayland") || wayland_display.to_lowercase().contains("wayland")
+}
+
+fn has_dbus_name(conn: &Connection, name: &str) -> bool {
+    let proxy = conn.with_proxy(
+        "org.freedesktop.DBus",
+        "/org/freedesktop/DBus",
+        Duration::from_millis(1000),
+    );
+
+    let has_owner: Result<(bool,), _> =
+        proxy.method_call("org.freedesktop.DBus", "NameHasOwner", (name,));
+    if matches!(has_owner, Ok((true,))) {
+        return true;
+    }
+
+    // 服务可能还没启动，但可以通过 D-Bus 激活
+    let activatable_names: Result<(Vec<String>,), _> =
+        proxy.method_call("org.freedesktop.DBus", "ListActivatableNames", ());
+
+    activatable_names.is_ok_and(|(names,)| names.iter().any(|activatable| activatable == name))
+}
+
+struct WaylandGlobals;
+
+impl Dispatch<WlRegistry, GlobalListContents> for WaylandGlobals {
+    fn event(
+        _state: &mut Self,
+        _registry: &WlRegistry,
+        _event: wl_registry::Event,
+        _data: &GlobalListContents,
+        _conn: &WaylandConnection,
+        _qh: &QueueHandle<Self>,
+    ) {
+    }
+}
+
+/// 合成器提供的全部 Wayland 全局对象的接口名
+fn wayland_globals() -> Vec<String> {
+    let Ok(conn) = WaylandConnection::connect_to_env() else {
+        return Vec::new();
+    };
+
+    match registry_queue_init::<WaylandGlobals>(&conn) {
+        Ok((globals, _)) => globals
+            .contents()
+            .clone_list()
+            .into_iter()
+            .map(|global| global.interface)
+            .collect(),
+        Err(err) => {
+            log::debug!("Get wayland globals failed: {}", err);
+            Vec::new()
+        }
+    }
+}
+
+fn detect_backends() -> Vec<Backend> {
+    let mut backends = Vec::new();
+
+    if wayland_detect() {
+        let wayland_globals = wayland_globals();
+        let has_global = |interface: &str| wayland_globals.iter().any(|name| name == interface);
+
+        if has_global("ext_image_copy_capture_manager_v1")
+            && has_global("ext_output_image_capture_source_manager_v1")
+        {
+            backends.push(Backend::ExtImageCopyCapture);
+        }
+        if has_global("zwlr_screencopy_manager_v1") {
+            backends.push(Backend::WlrScreencopy);
+        }
+    } else if var_os("DISPLAY").is_some() {
+        backends.push(Backend::X11);
+    }
+
+    // X11 会话直接使用 X server，不需要通过 D-Bus 查询合成器的截图服务
+    if !wayland_detect() && !backends.is_empty() {
+        return backends;
+    }
+
+    if let Ok(conn) = Connection::new_session() {
+        if has_dbus_name(&conn, "org.kde.KWin") {
+            backends.push(Backend::KWin);
+        }
+        if has_dbus_name(&conn, "org.gnome.Shell.Screenshot") {
+            backends.push(Backend::GnomeShell);
+        }
+        if has_dbus_name(&conn, "org.freedesktop.portal.Desktop") {
+            // ScreenCast 只在建立会话时询问一次，优先于每次都可能弹窗的 Screenshot
+            #[cfg(feature = "pipewire")]
+            backends.push(Backend::PipeWire);
+            backends.push(Backend::FreedesktopPortal);
+        }
+    }
+
+    backends
+}
+
+/// 会话中可用的后端在进程生命周期内不会变化，只检测一次
+pub fn available_backends() -> &'static [Backend] {
+    static AVAILABLE_BACKENDS: OnceLock<Vec<Backend>> = OnceLock::new();
+
+    AVAILABLE_BACKENDS.get_or_init(detect_backends)
+}
+
+/// 按顺序返回要尝试的后端：调用时指定的后端 > `set_backend` 设置的后端 > 自动检测到的后端
+pub fn capture_backends(
+    backend: Option<Backend>,
+    feature: &'static str,
+    supports: impl Fn(&Capabilities) -> bool,
+) -> XCapResult<Vec<Backend>> {
+    if let Some(backend) = backend.or_else(selected_backend) {
+        if !available_backends().contains(&backend) || !supports(&backend.capabilities()) {
+            return Err(XCapError::Unsupported {
+                feature,
+                backend: backend.name(),
+            });
+        }
+
+        return Ok(vec![backend]);
+    }
+
+    let mut backends: Vec<Backend> = available_backends()
+        .iter()
+        .copied()
+        .filter(|backend| supports(&backend.capabilities()))
+        .collect();
+
+    // 可能弹窗的后端放在最后，其它后端都失败（例如调用方不在 GNOME Shell、KWin 的白名单中）时才使用
+    backends.sort_by_key(|backend| !backend.capabilities().non_interactive);
+
+    if backends.is_empty() {
+        return Err(match available_backends().first() {
+            Some(backend) => XCapError::Unsupported {
+                feature,
+                backend: backend.name(),
+            },
+            None => XCapError::NoDisplay(String::from("No capture backend available")),
+        });
+    }
+
+    Ok(backends)
+}
+
+/// 录屏、损坏区域跟踪等功能只有 X11 实现
+pub fn require_x11(feature: &'static str) -> XCapResult<()> {
+    let backend = current_backend()?;
+
+    if backend != Backend::X11 {
+        return Err(XCapError::Unsupported {
+            feature,
+            backend: backend.name(),
+        });
+    }
+
+    Ok(())
+}
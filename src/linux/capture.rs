@@ -1,57 +1,690 @@
 use image::RgbaImage;
-use std::env::var_os;
+use std::sync::mpsc::{self, Receiver};
+use xcb::{
+    x::{self, Drawable, GetGeometry, TranslateCoordinates, Window},
+    Connection, ProtocolError, Xid,
+};
 
-use crate::error::XCapResult;
+use crate::{
+    backend::{current_backend, Backend},
+    capture_options::{CaptureOptions, WindowCaptureMode, X11CaptureMethod},
+    error::{XCapError, XCapResult},
+    frame::Frame,
+    frame_buffer::FrameBuffer,
+    rect::Rect,
+    video_recorder::{VideoRecorder, WindowRecorderEvent},
+    VirtualScreen,
+};
 
 use super::{
-    impl_monitor::ImplMonitor, impl_window::ImplWindow, wayland_capture::wayland_capture,
-    xorg_capture::xorg_capture,
+    backend::{capture_backends, require_x11},
+    composite_capture::{composite_capture, get_toplevel_window},
+    ext_capture::{ext_capture_output, ext_capture_toplevel},
+    impl_cursor::ImplCursor,
+    impl_monitor::ImplMonitor,
+    impl_window::{get_active_window, ImplWindow},
+    kwin_capture::{
+        kwin_capture_active_window, kwin_capture_area, kwin_capture_screen, kwin_capture_window,
+    },
+    wayland_capture::{gnome_shell_capture, portal_capture},
+    wlr_capture::wlr_capture,
+    xorg_connection::XorgConnection,
 };
 
-fn wayland_detect() -> bool {
-    let xdg_session_type = var_os("XDG_SESSION_TYPE")
-        .unwrap_or_default()
-        .to_string_lossy()
-        .to_string();
+#[cfg(feature = "pipewire")]
+use super::pipewire_capture::pipewire_capture;
 
-    let wayland_display = var_os("WAYLAND_DISPLAY")
-        .unwrap_or_default()
-        .to_string_lossy()
-        .to_string();
+pub fn capture_monitor(impl_monitor: &ImplMonitor) -> XCapResult<RgbaImage> {
+    let backends = capture_backends(None, "Monitor capture", |_| true)?;
 
-    xdg_session_type.eq("wayland") || wayland_display.to_lowercase().contains("wayland")
+    capture_monitor_area(
+        impl_monitor,
+        &backends,
+        0,
+        0,
+        impl_monitor.width,
+        impl_monitor.height,
+        false,
+    )?
+    .into_rgba_image()
 }
 
-pub fn capture_monitor(impl_monitor: &ImplMonitor) -> XCapResult<RgbaImage> {
-    if wayland_detect() {
-        wayland_capture(impl_monitor)
+/// 请求返回的 X 错误，`xcb::Error` 与 `xcb::ProtocolError` 转换得到的都算
+fn x_error(err: &XCapError) -> Option<&x::Error> {
+    match err {
+        XCapError::XcbError(xcb::Error::Protocol(ProtocolError::X(x_error, _)))
+        | XCapError::XcbProtocolError(ProtocolError::X(x_error, _)) => Some(x_error),
+        _ => None,
+    }
+}
+
+/// 截图 API 共用的 X 连接上一次截图实际使用的方式
+pub fn last_capture_method() -> Option<X11CaptureMethod> {
+    XorgConnection::shared_last_capture_method()
+}
+
+fn map_access_error(err: XCapError) -> XCapError {
+    match x_error(&err) {
+        Some(x::Error::Access(_)) => XCapError::PermissionDenied(err.to_string()),
+        _ => err,
+    }
+}
+
+/// 显示器断开后 root window 会缩小，读取超出范围的区域会得到 BadMatch
+fn map_monitor_error(err: XCapError, monitor_id: u32) -> XCapError {
+    match x_error(&err) {
+        Some(x::Error::Match(_)) => XCapError::MonitorGone(monitor_id),
+        _ => map_access_error(err),
+    }
+}
+
+fn map_kwin_window_error(err: XCapError, window_id: u32) -> XCapError {
+    match err {
+        XCapError::DbusError(ref dbus_err)
+            if dbus_err.name() == Some("org.kde.KWin.ScreenShot2.Error.InvalidWindow") =>
+        {
+            XCapError::WindowGone(window_id)
+        }
+        err => err,
+    }
+}
+
+fn map_window_error(err: XCapError, window: Window) -> XCapError {
+    match x_error(&err) {
+        Some(x::Error::Window(_) | x::Error::Drawable(_)) => {
+            XCapError::WindowGone(window.resource_id())
+        }
+        _ => map_access_error(err),
+    }
+}
+
+pub fn capture_monitor_region(
+    impl_monitor: &ImplMonitor,
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+) -> XCapResult<RgbaImage> {
+    let backends = capture_backends(None, "Region capture", |capabilities| {
+        capabilities.region_capture
+    })?;
+
+    capture_monitor_area(impl_monitor, &backends, x, y, width, height, false)?.into_rgba_image()
+}
+
+/// 把相对于显示器的逻辑区域换算为 root window 中的物理像素区域
+fn x11_monitor_region(impl_monitor: &ImplMonitor, region: Rect) -> (i32, i32, u32, u32) {
+    let x = (((impl_monitor.x + region.x) as f32) * impl_monitor.scale_factor) as i32;
+    let y = (((impl_monitor.y + region.y) as f32) * impl_monitor.scale_factor) as i32;
+    let width = ((region.width as f32) * impl_monitor.scale_factor) as u32;
+    let height = ((region.height as f32) * impl_monitor.scale_factor) as u32;
+
+    (x, y, width, height)
+}
+
+/// 在已有的 X 连接上截取显示器上的区域，`x`、`y` 相对于显示器左上角
+pub fn capture_x11_monitor_region_into(
+    conn: &XorgConnection,
+    impl_monitor: &ImplMonitor,
+    region: Rect,
+    frame_buffer: &mut FrameBuffer,
+) -> XCapResult<()> {
+    let (x, y, width, height) = x11_monitor_region(impl_monitor, region);
+
+    conn.capture_into(
+        Drawable::Window(impl_monitor.root()?),
+        x,
+        y,
+        width,
+        height,
+        frame_buffer,
+    )
+    .map_err(|err| map_monitor_error(err, impl_monitor.id))
+}
+
+/// X11 保留 X server 的像素格式，其它后端保留合成器返回的像素格式
+pub fn capture_monitor_frame(impl_monitor: &ImplMonitor) -> XCapResult<Frame> {
+    let backends = capture_backends(None, "Monitor capture", |_| true)?;
+
+    if backends[0] == Backend::X11 {
+        let conn = XorgConnection::shared()?;
+        let (x, y, width, height) = x11_monitor_region(
+            impl_monitor,
+            Rect::new(0, 0, impl_monitor.width, impl_monitor.height),
+        );
+
+        return conn
+            .capture_frame(Drawable::Window(impl_monitor.root()?), x, y, width, height)
+            .map_err(|err| map_monitor_error(err, impl_monitor.id));
+    }
+
+    capture_monitor_area(
+        impl_monitor,
+        &backends,
+        0,
+        0,
+        impl_monitor.width,
+        impl_monitor.height,
+        false,
+    )
+}
+
+/// X11 直接转换到调用方的内存中，其它后端把截图的内存交给 frame_buffer
+pub fn capture_monitor_into(
+    impl_monitor: &ImplMonitor,
+    frame_buffer: &mut FrameBuffer,
+) -> XCapResult<()> {
+    let backends = capture_backends(None, "Monitor capture", |_| true)?;
+
+    if backends[0] == Backend::X11 {
+        let conn = XorgConnection::shared()?;
+        let region = Rect::new(0, 0, impl_monitor.width, impl_monitor.height);
+
+        return capture_x11_monitor_region_into(&conn, impl_monitor, region, frame_buffer);
+    }
+
+    let rgba_image = capture_monitor_area(
+        impl_monitor,
+        &backends,
+        0,
+        0,
+        impl_monitor.width,
+        impl_monitor.height,
+        false,
+    )?
+    .into_rgba_image()?;
+    frame_buffer.set_rgba_image(rgba_image);
+
+    Ok(())
+}
+
+/// X11 下用 XFixes 拿到的光标合成到截图上，`x`、`y` 是截图左上角在 root window 中的坐标
+fn composite_cursor(rgba_image: &mut RgbaImage, x: i32, y: i32) -> XCapResult<()> {
+    let impl_cursor = ImplCursor::new()?;
+    impl_cursor.composite(rgba_image, x, y);
+
+    Ok(())
+}
+
+/// 依次尝试各个后端，直到有一个截图成功。用户取消或显示器断开时不再尝试其它后端，
+/// 没有权限只针对当前后端（例如 GNOME Shell、KWin 的白名单），继续尝试下一个
+fn capture_monitor_area(
+    impl_monitor: &ImplMonitor,
+    backends: &[Backend],
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+    include_cursor: bool,
+) -> XCapResult<Frame> {
+    // Wayland 后端按显示器截图，使用相对于显示器的逻辑坐标
+    let monitor_rect = Rect::new(
+        impl_monitor.x,
+        impl_monitor.y,
+        impl_monitor.width,
+        impl_monitor.height,
+    );
+    let region_rect = Rect::new(x as i32, y as i32, width, height);
+
+    let x = (((impl_monitor.x + x as i32) as f32) * impl_monitor.scale_factor) as i32;
+    let y = (((impl_monitor.y + y as i32) as f32) * impl_monitor.scale_factor) as i32;
+    let width = ((width as f32) * impl_monitor.scale_factor) as u32;
+    let height = ((height as f32) * impl_monitor.scale_factor) as u32;
+
+    let mut last_err = XCapError::NoDisplay(String::from("No capture backend available"));
+
+    for &backend in backends {
+        let result = match backend {
+            Backend::X11 => impl_monitor
+                .root()
+                .and_then(|root| {
+                    XorgConnection::shared()?.capture(Drawable::Window(root), x, y, width, height)
+                })
+                .map_err(|err| map_monitor_error(err, impl_monitor.id))
+                .and_then(|mut rgba_image| {
+                    if include_cursor {
+                        composite_cursor(&mut rgba_image, x, y)?;
+                    }
+                    Ok(Frame::from_rgba_image(rgba_image))
+                }),
+            Backend::ExtImageCopyCapture => ext_capture_output(
+                &impl_monitor.name,
+                monitor_rect,
+                region_rect,
+                include_cursor,
+            ),
+            Backend::WlrScreencopy => wlr_capture(
+                &impl_monitor.name,
+                monitor_rect,
+                region_rect,
+                include_cursor,
+            ),
+            Backend::KWin
+                if region_rect == Rect::new(0, 0, monitor_rect.width, monitor_rect.height) =>
+            {
+                kwin_capture_screen(&impl_monitor.name, include_cursor)
+            }
+            Backend::KWin => kwin_capture_area(
+                monitor_rect.x + region_rect.x,
+                monitor_rect.y + region_rect.y,
+                region_rect.width,
+                region_rect.height,
+                include_cursor,
+            ),
+            Backend::GnomeShell => {
+                gnome_shell_capture(x, y, width as i32, height as i32).map(Frame::from_rgba_image)
+            }
+            Backend::FreedesktopPortal => {
+                portal_capture(x, y, width as i32, height as i32).map(Frame::from_rgba_image)
+            }
+            #[cfg(feature = "pipewire")]
+            Backend::PipeWire => pipewire_capture(monitor_rect, region_rect),
+            _ => Err(XCapError::Unsupported {
+                feature: "Monitor capture",
+                backend: backend.name(),
+            }),
+        };
+
+        match result {
+            Ok(frame) => return Ok(frame),
+            // 换一个后端也不会成功，不再尝试
+            Err(err @ (XCapError::UserCancelled | XCapError::MonitorGone(_))) => return Err(err),
+            Err(err) => {
+                log::debug!("{} capture failed: {}", backend.name(), err);
+                last_err = err;
+            }
+        }
+    }
+
+    Err(last_err)
+}
+
+pub fn capture_monitor_with_options(
+    impl_monitor: &ImplMonitor,
+    options: &CaptureOptions,
+) -> XCapResult<RgbaImage> {
+    let backends = if options.include_cursor {
+        capture_backends(options.backend, "Cursor capture", |capabilities| {
+            capabilities.cursor
+        })?
     } else {
-        let x = ((impl_monitor.x as f32) * impl_monitor.scale_factor) as i32;
-        let y = ((impl_monitor.y as f32) * impl_monitor.scale_factor) as i32;
-        let width = ((impl_monitor.width as f32) * impl_monitor.scale_factor) as u32;
-        let height = ((impl_monitor.height as f32) * impl_monitor.scale_factor) as u32;
+        capture_backends(options.backend, "Monitor capture", |_| true)?
+    };
+
+    // 只有支持光标的后端会被选中：X11 用 XFixes 合成，Wayland 后端由合成器绘制
+    capture_monitor_area(
+        impl_monitor,
+        &backends,
+        0,
+        0,
+        impl_monitor.width,
+        impl_monitor.height,
+        options.include_cursor,
+    )?
+    .into_rgba_image()
+}
 
-        xorg_capture(impl_monitor.screen_buf.root(), x, y, width, height)
+pub fn monitor_video_recorder(
+    impl_monitor: &ImplMonitor,
+) -> XCapResult<(VideoRecorder, Receiver<XCapResult<Frame>>)> {
+    let backends = capture_backends(None, "Video recording", |capabilities| {
+        capabilities.video_recording
+    })?;
+
+    let monitor_rect = Rect::new(
+        impl_monitor.x,
+        impl_monitor.y,
+        impl_monitor.width,
+        impl_monitor.height,
+    );
+    let region_rect = Rect::new(0, 0, impl_monitor.width, impl_monitor.height);
+
+    match backends[0] {
+        Backend::ExtImageCopyCapture => {
+            let name = impl_monitor.name.clone();
+
+            Ok(VideoRecorder::from_capture(move || {
+                ext_capture_output(&name, monitor_rect, region_rect, false)?.into_rgba_image()
+            }))
+        }
+        Backend::WlrScreencopy => {
+            let name = impl_monitor.name.clone();
+
+            Ok(VideoRecorder::from_capture(move || {
+                wlr_capture(&name, monitor_rect, region_rect, false)?.into_rgba_image()
+            }))
+        }
+        #[cfg(feature = "pipewire")]
+        Backend::PipeWire => Ok(VideoRecorder::from_capture(move || {
+            pipewire_capture(monitor_rect, region_rect)?.into_rgba_image()
+        })),
+        _ => {
+            let root = impl_monitor.root()?;
+            let x = ((impl_monitor.x as f32) * impl_monitor.scale_factor) as i32;
+            let y = ((impl_monitor.y as f32) * impl_monitor.scale_factor) as i32;
+            let width = ((impl_monitor.width as f32) * impl_monitor.scale_factor) as u32;
+            let height = ((impl_monitor.height as f32) * impl_monitor.scale_factor) as u32;
+
+            // 录制期间复用同一个连接与共享内存段
+            let conn = XorgConnection::connect()?;
+
+            Ok(VideoRecorder::from_capture(move || {
+                conn.capture(Drawable::Window(root), x, y, width, height)
+            }))
+        }
     }
 }
 
-pub fn capture_window(impl_window: &ImplWindow) -> XCapResult<RgbaImage> {
-    let width = impl_window.width;
-    let height = impl_window.height;
+pub fn capture_virtual_screen(virtual_screen: &VirtualScreen) -> XCapResult<RgbaImage> {
+    if current_backend()? != Backend::X11 {
+        return virtual_screen.stitch_monitor_images();
+    }
 
-    xorg_capture(impl_window.window, 0, 0, width, height)
+    let impl_monitors: Vec<&ImplMonitor> = virtual_screen
+        .monitors()
+        .iter()
+        .map(|monitor| &monitor.impl_monitor)
+        .collect();
+
+    let first = impl_monitors
+        .first()
+        .ok_or_else(|| XCapError::new("Not found monitor"))?;
+
+    // X11 下所有显示器共用同一个 root window 与缩放比例，一次 GetImage 即可
+    let scale_factor = first.scale_factor;
+    let x = ((virtual_screen.x() as f32) * scale_factor) as i32;
+    let y = ((virtual_screen.y() as f32) * scale_factor) as i32;
+    let width = ((virtual_screen.width() as f32) * scale_factor) as u32;
+    let height = ((virtual_screen.height() as f32) * scale_factor) as u32;
+
+    let mut rgba_image =
+        XorgConnection::shared()?.capture(Drawable::Window(first.root()?), x, y, width, height)?;
+
+    let monitor_rects: Vec<(i32, i32, i32, i32)> = impl_monitors
+        .iter()
+        .map(|impl_monitor| {
+            let left = ((impl_monitor.x as f32) * scale_factor) as i32 - x;
+            let top = ((impl_monitor.y as f32) * scale_factor) as i32 - y;
+            let right = left + ((impl_monitor.width as f32) * scale_factor) as i32;
+            let bottom = top + ((impl_monitor.height as f32) * scale_factor) as i32;
+
+            (left, top, right, bottom)
+        })
+        .collect();
+
+    // 不被任何显示器覆盖的区域内容是未定义的，置为透明
+    for (px, py, pixel) in rgba_image.enumerate_pixels_mut() {
+        let (px, py) = (px as i32, py as i32);
+        let is_covered = monitor_rects.iter().any(|&(left, top, right, bottom)| {
+            px >= left && px < right && py >= top && py < bottom
+        });
+
+        if !is_covered {
+            pixel.0 = [0, 0, 0, 0];
+        }
+    }
+
+    Ok(rgba_image)
+}
+
+/// 根据截图模式计算截图区域，坐标相对于窗口客户区左上角
+fn get_window_capture_rect(
+    impl_window: &ImplWindow,
+    mode: WindowCaptureMode,
+) -> XCapResult<(i32, i32, u32, u32)> {
+    let frame_extents = impl_window.frame_extents;
+    let [left, right, top, bottom] = match mode {
+        WindowCaptureMode::Client => return Ok((0, 0, impl_window.width, impl_window.height)),
+        // 只向外扩展窗口管理器的装饰，保留客户端绘制的阴影
+        WindowCaptureMode::Frame => [
+            frame_extents.left.max(0),
+            frame_extents.right.max(0),
+            frame_extents.top.max(0),
+            frame_extents.bottom.max(0),
+        ],
+        WindowCaptureMode::VisibleFrame => [
+            frame_extents.left,
+            frame_extents.right,
+            frame_extents.top,
+            frame_extents.bottom,
+        ],
+    };
+
+    let width = impl_window.width as i32 + left + right;
+    let height = impl_window.height as i32 + top + bottom;
+
+    if width <= 0 || height <= 0 {
+        return Err(XCapError::new(format!(
+            "Invalid frame extents {:?} for window size {}x{}",
+            frame_extents, impl_window.width, impl_window.height
+        )));
+    }
+
+    Ok((-left, -top, width as u32, height as u32))
+}
+
+pub fn capture_window(impl_window: &ImplWindow, mode: WindowCaptureMode) -> XCapResult<RgbaImage> {
+    let backend = capture_backends(None, "Window capture", |capabilities| {
+        capabilities.window_capture
+    })
+    .map(|backends| backends[0]);
+    let include_decoration = mode != WindowCaptureMode::Client;
+
+    if let Some(identifier) = &impl_window.wayland_identifier {
+        return capture_wayland_window(impl_window, identifier, backend, include_decoration)?
+            .into_rgba_image();
+    }
+
+    let conn = XorgConnection::shared()?;
+
+    if is_kwin_active_window(&conn, impl_window, &backend) {
+        return kwin_capture_active_window(include_decoration)?.into_rgba_image();
+    }
+
+    capture_x11_window(&conn, impl_window, mode)
+}
+
+/// Wayland 窗口由合成器截取，包含合成器绘制的装饰，ext-image-copy-capture 不区分截图模式
+fn capture_wayland_window(
+    impl_window: &ImplWindow,
+    identifier: &str,
+    backend: XCapResult<Backend>,
+    include_decoration: bool,
+) -> XCapResult<Frame> {
+    // KWin 的 ext-foreign-toplevel-list 使用窗口的内部 id 作为 identifier
+    if let Ok(Backend::KWin) = backend {
+        return kwin_capture_window(identifier, include_decoration)
+            .map_err(|err| map_kwin_window_error(err, impl_window.id));
+    }
+
+    ext_capture_toplevel(identifier)?.ok_or(XCapError::WindowGone(impl_window.id))
+}
+
+/// XWayland 中读不到 KWin 绘制的窗口装饰，焦点窗口交给 KWin 截取。
+/// `is_focused` 是获取窗口列表时的状态，截图前重新读取 _NET_ACTIVE_WINDOW，避免截到其它窗口
+fn is_kwin_active_window(
+    conn: &XorgConnection,
+    impl_window: &ImplWindow,
+    backend: &XCapResult<Backend>,
+) -> bool {
+    if !matches!(backend, Ok(Backend::KWin)) {
+        return false;
+    }
+
+    let Ok(active_window_atom) = conn.atom("_NET_ACTIVE_WINDOW") else {
+        return false;
+    };
+
+    conn.get_setup().roots().any(|screen| {
+        get_active_window(conn, screen.root(), active_window_atom) == Some(impl_window.window)
+    })
+}
+
+/// 在已有的 X 连接上截取 X11 窗口
+pub fn capture_x11_window(
+    conn: &XorgConnection,
+    impl_window: &ImplWindow,
+    mode: WindowCaptureMode,
+) -> XCapResult<RgbaImage> {
+    capture_window_rect(conn, impl_window, mode, |drawable, x, y, width, height| {
+        conn.capture(drawable, x, y, width, height)
+    })
+    .map_err(|err| map_window_error(err, impl_window.window))
+}
+
+/// X11 窗口保留 X server 的像素格式，KWin 保留合成器返回的像素格式
+pub fn capture_window_frame(impl_window: &ImplWindow) -> XCapResult<Frame> {
+    let backend = capture_backends(None, "Window capture", |capabilities| {
+        capabilities.window_capture
+    })
+    .map(|backends| backends[0]);
+
+    if let Some(identifier) = &impl_window.wayland_identifier {
+        return capture_wayland_window(impl_window, identifier, backend, false);
+    }
+
+    let conn = XorgConnection::shared()?;
+
+    if is_kwin_active_window(&conn, impl_window, &backend) {
+        return kwin_capture_active_window(false);
+    }
+
+    capture_window_rect(
+        &conn,
+        impl_window,
+        WindowCaptureMode::Client,
+        |drawable, x, y, width, height| conn.capture_frame(drawable, x, y, width, height),
+    )
+    .map_err(|err| map_window_error(err, impl_window.window))
 }
 
-// fn capture_screen_area(
-//     screen_info: &ScreenInfo,
-//     x: i32,
-//     y: i32,
-//     width: u32,
-//     height: u32,
-// ) -> XCapResult<RgbaImage> {
-//     if wayland_detect() {
-//         wayland_capture_screen_area(screen_info, x, y, width, height)
-//     } else {
-//         xorg_capture_screen_area(screen_info, x, y, width, height)
-//     }
-// }
+/// `capture` 接收要读取的 drawable 与其中的区域
+fn capture_window_rect<T>(
+    conn: &XorgConnection,
+    impl_window: &ImplWindow,
+    mode: WindowCaptureMode,
+    capture: impl Fn(Drawable, i32, i32, u32, u32) -> XCapResult<T>,
+) -> XCapResult<T> {
+    let (x, y, width, height) = get_window_capture_rect(impl_window, mode)?;
+
+    match composite_capture(
+        conn,
+        conn.screen_num(),
+        impl_window.window,
+        x,
+        y,
+        |drawable, x, y| capture(drawable, x, y, width, height),
+    ) {
+        Ok(result) => return Ok(result),
+        Err(err) => log::debug!("Composite capture failed, fallback to GetImage: {}", err),
+    }
+
+    if mode == WindowCaptureMode::Client {
+        return capture(Drawable::Window(impl_window.window), 0, 0, width, height);
+    }
+
+    // 窗口装饰在客户窗口之外，需要从窗口管理器的顶层窗口读取
+    let toplevel = get_toplevel_window(conn, impl_window.window)?;
+    let translate_coordinates_cookie = conn.send_request(&TranslateCoordinates {
+        src_window: impl_window.window,
+        dst_window: toplevel,
+        src_x: 0,
+        src_y: 0,
+    });
+    let translate_coordinates_reply = conn.wait_for_reply(translate_coordinates_cookie)?;
+
+    capture(
+        Drawable::Window(toplevel),
+        translate_coordinates_reply.dst_x() as i32 + x,
+        translate_coordinates_reply.dst_y() as i32 + y,
+        width,
+        height,
+    )
+}
+
+fn get_window_size(conn: &Connection, window: Window) -> XCapResult<(u32, u32)> {
+    let get_geometry_cookie = conn.send_request(&GetGeometry {
+        drawable: Drawable::Window(window),
+    });
+    let get_geometry_reply = conn.wait_for_reply(get_geometry_cookie)?;
+
+    Ok((
+        get_geometry_reply.width() as u32,
+        get_geometry_reply.height() as u32,
+    ))
+}
+
+pub fn window_video_recorder(
+    impl_window: &ImplWindow,
+) -> XCapResult<(VideoRecorder, Receiver<WindowRecorderEvent>)> {
+    require_x11("Video recording")?;
+
+    // 连续失败这么多帧后结束录制，避免窗口一直无法截取时录制线程空转
+    const MAX_CAPTURE_FAILURES: u32 = 30;
+
+    let window = impl_window.window;
+    let mut impl_window = impl_window.clone();
+    let mut capture_failures = 0;
+    let conn = XorgConnection::connect()?;
+    let (sender, receiver) = mpsc::channel();
+
+    let window_gone = move |err: XCapError| {
+        log::debug!("Window {:?} is gone: {}", window, err);
+        XCapError::WindowGone(window.resource_id())
+    };
+
+    let video_recorder = VideoRecorder::spawn(move |timestamp| {
+        // 窗口销毁后 GetGeometry 会失败，以错误结束录制
+        let (width, height) = match get_window_size(&conn, window) {
+            Ok(size) => size,
+            Err(err) => {
+                let _ = sender.send(WindowRecorderEvent::Closed(window_gone(err)));
+                return false;
+            }
+        };
+
+        if (width, height) != (impl_window.width, impl_window.height) {
+            impl_window.width = width;
+            impl_window.height = height;
+            let resized = WindowRecorderEvent::Resized { width, height };
+            if sender.send(resized).is_err() {
+                return false;
+            }
+        }
+
+        // 与截图相同，优先读取 Composite 的 pixmap，部分移出屏幕的窗口也能截取
+        let result = capture_window_rect(
+            &conn,
+            &impl_window,
+            WindowCaptureMode::Client,
+            |drawable, x, y, width, height| conn.capture(drawable, x, y, width, height),
+        );
+
+        match result {
+            Ok(rgba_image) => {
+                capture_failures = 0;
+                let frame = Frame::new(timestamp, rgba_image);
+                sender.send(WindowRecorderEvent::Frame(frame)).is_ok()
+            }
+            // 窗口可能在 GetGeometry 与截图之间被销毁
+            Err(err) => match get_window_size(&conn, window) {
+                Ok(_) if capture_failures + 1 < MAX_CAPTURE_FAILURES => {
+                    capture_failures += 1;
+                    log::error!("Capture window {:?} failed: {}", window, err);
+                    true
+                }
+                Ok(_) => {
+                    let err = map_window_error(err, window);
+                    let _ = sender.send(WindowRecorderEvent::Closed(err));
+                    false
+                }
+                Err(_) => {
+                    let _ = sender.send(WindowRecorderEvent::Closed(window_gone(err)));
+                    false
+                }
+            },
+        }
+    });
+
+    Ok((video_recorder, receiver))
+}
@@ -0,0 +1,136 @@
+use xcb::{
+    composite,
+    x::{
+        Drawable, FreePixmap, GetGeometry, GetSelectionOwner, InternAtom, QueryTree,
+        TranslateCoordinates, Window, ATOM_NONE,
+    },
+    Connection, Extension, Xid,
+};
+
+use crate::error::{XCapError, XCapResult};
+
+// https://specifications.freedesktop.org/wm-spec/1.3/ar01s08.html#id-1.9.10
+fn is_compositing_manager_running(conn: &Connection, screen_num: i32) -> XCapResult<bool> {
+    let name = format!("_NET_WM_CM_S{}", screen_num);
+    let intern_atom_cookie = conn.send_request(&InternAtom {
+        only_if_exists: true,
+        name: name.as_bytes(),
+    });
+    let atom = conn.wait_for_reply(intern_atom_cookie)?.atom();
+
+    if atom == ATOM_NONE {
+        return Ok(false);
+    }
+
+    let get_selection_owner_cookie = conn.send_request(&GetSelectionOwner { selection: atom });
+    let owner = conn.wait_for_reply(get_selection_owner_cookie)?.owner();
+
+    Ok(!owner.is_none())
+}
+
+/// 向上查找 root 的直接子窗口，也就是窗口管理器的装饰窗口
+pub(super) fn get_toplevel_window(conn: &Connection, window: Window) -> XCapResult<Window> {
+    let mut current = window;
+
+    loop {
+        let query_tree_cookie = conn.send_request(&QueryTree { window: current });
+        let query_tree_reply = conn.wait_for_reply(query_tree_cookie)?;
+
+        if query_tree_reply.parent() == query_tree_reply.root()
+            || query_tree_reply.parent().is_none()
+        {
+            return Ok(current);
+        }
+
+        current = query_tree_reply.parent();
+    }
+}
+
+fn capture_toplevel_pixmap<T>(
+    conn: &Connection,
+    window: Window,
+    toplevel: Window,
+    x: i32,
+    y: i32,
+    capture: impl FnOnce(Drawable, i32, i32) -> XCapResult<T>,
+) -> XCapResult<T> {
+    let get_geometry_cookie = conn.send_request(&GetGeometry {
+        drawable: Drawable::Window(toplevel),
+    });
+    let border_width = conn.wait_for_reply(get_geometry_cookie)?.border_width() as i32;
+
+    let translate_coordinates_cookie = conn.send_request(&TranslateCoordinates {
+        src_window: window,
+        dst_window: toplevel,
+        src_x: 0,
+        src_y: 0,
+    });
+    let translate_coordinates_reply = conn.wait_for_reply(translate_coordinates_cookie)?;
+
+    let pixmap = conn.generate_id();
+    conn.send_and_check_request(&composite::NameWindowPixmap {
+        window: toplevel,
+        pixmap,
+    })?;
+
+    // pixmap 包含边框，窗口内容从边框内开始
+    let result = capture(
+        Drawable::Pixmap(pixmap),
+        translate_coordinates_reply.dst_x() as i32 + border_width + x,
+        translate_coordinates_reply.dst_y() as i32 + border_width + y,
+    );
+
+    conn.send_request(&FreePixmap { pixmap });
+
+    result
+}
+
+/// 通过 Composite 扩展读取窗口自身的 backing pixmap，被遮挡或超出屏幕的部分也能正确截取。
+/// 只有合成管理器运行时 pixmap 中才有完整内容，否则返回错误由调用方回退到 GetImage。
+/// x、y 相对于窗口客户区左上角，可以为负数以包含窗口装饰。
+/// `capture` 接收 pixmap 与换算后的起点坐标，在 pixmap 释放前完成读取
+pub fn composite_capture<T>(
+    conn: &Connection,
+    screen_num: i32,
+    window: Window,
+    x: i32,
+    y: i32,
+    capture: impl FnOnce(Drawable, i32, i32) -> XCapResult<T>,
+) -> XCapResult<T> {
+    let is_composite_active = conn
+        .active_extensions()
+        .any(|extension| extension == Extension::Composite);
+
+    if !is_composite_active {
+        return Err(XCapError::new("Composite extension not available"));
+    }
+
+    if !is_compositing_manager_running(conn, screen_num)? {
+        return Err(XCapError::new("No compositing manager running"));
+    }
+
+    // NameWindowPixmap 需要 0.2 以上版本
+    let query_version_cookie = conn.send_request(&composite::QueryVersion {
+        client_major_version: 0,
+        client_minor_version: 4,
+    });
+    conn.wait_for_reply(query_version_cookie)?;
+
+    let toplevel = get_toplevel_window(conn, window)?;
+
+    // 合成管理器已经重定向了顶层窗口，这里再自动重定向一次以确保 pixmap 存在
+    conn.send_and_check_request(&composite::RedirectWindow {
+        window: toplevel,
+        update: composite::Redirect::Automatic,
+    })?;
+
+    let result = capture_toplevel_pixmap(conn, window, toplevel, x, y, capture);
+
+    conn.send_request(&composite::UnredirectWindow {
+        window: toplevel,
+        update: composite::Redirect::Automatic,
+    });
+    conn.flush()?;
+
+    result
+}
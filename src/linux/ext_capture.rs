@@ -0,0 +1,494 @@
+use wayland_client::{
+    delegate_dispatch, delegate_noop, event_created_child,
+    globals::{registry_queue_init, GlobalList, GlobalListContents},
+    protocol::{
+        wl_buffer::WlBuffer,
+        wl_output::{Transform, WlOutput},
+        wl_registry::{self, WlRegistry},
+        wl_shm::{Format, WlShm},
+        wl_shm_pool::WlShmPool,
+    },
+    Connection, Dispatch, EventQueue, QueueHandle, WEnum,
+};
+use wayland_protocols::{
+    ext::{
+        foreign_toplevel_list::v1::client::{
+            ext_foreign_toplevel_handle_v1::{self, ExtForeignToplevelHandleV1},
+            ext_foreign_toplevel_list_v1::{self, ExtForeignToplevelListV1},
+        },
+        image_capture_source::v1::client::{
+            ext_foreign_toplevel_image_capture_source_manager_v1::ExtForeignToplevelImageCaptureSourceManagerV1,
+            ext_image_capture_source_v1::ExtImageCaptureSourceV1,
+            ext_output_image_capture_source_manager_v1::ExtOutputImageCaptureSourceManagerV1,
+        },
+        image_copy_capture::v1::client::{
+            ext_image_copy_capture_frame_v1::{self, ExtImageCopyCaptureFrameV1, FailureReason},
+            ext_image_copy_capture_manager_v1::{ExtImageCopyCaptureManagerV1, Options},
+            ext_image_copy_capture_session_v1::{self, ExtImageCopyCaptureSessionV1},
+        },
+    },
+    xdg::xdg_output::zv1::client::{
+        zxdg_output_manager_v1::ZxdgOutputManagerV1, zxdg_output_v1::ZxdgOutputV1,
+    },
+};
+
+use crate::{
+    error::{XCapError, XCapResult},
+    frame::Frame,
+    rect::Rect,
+};
+
+use super::{
+    wayland_outputs::{
+        bind_outputs, find_output, to_output_region, WlOutputInfo, WlOutputs, WlOutputsHandler,
+    },
+    wayland_shm::{ShmBuffer, ShmFormat},
+};
+
+/// ext-foreign-toplevel-list 列出的一个顶层窗口
+#[derive(Debug, Clone)]
+pub struct ExtToplevel {
+    /// 合成器分配的唯一标识，窗口关闭前不会变化
+    pub identifier: String,
+    pub title: String,
+    pub app_id: String,
+    /// 窗口 buffer 的像素大小，合成器不支持截取窗口时为 0
+    pub width: u32,
+    pub height: u32,
+}
+
+#[derive(Debug)]
+struct ToplevelState {
+    handle: ExtForeignToplevelHandleV1,
+    identifier: String,
+    title: String,
+    app_id: String,
+    is_closed: bool,
+}
+
+/// 截图会话的 buffer 约束，在 done 事件之后才完整
+#[derive(Debug, Default)]
+struct SessionState {
+    buffer_size: Option<(u32, u32)>,
+    shm_format: Option<Format>,
+    is_done: bool,
+    is_stopped: bool,
+}
+
+#[derive(Debug, Default)]
+struct FrameState {
+    transform: Option<Transform>,
+    is_ready: bool,
+    failure_reason: Option<WEnum<FailureReason>>,
+}
+
+#[derive(Debug, Default)]
+struct ExtState {
+    outputs: Vec<WlOutputInfo>,
+    toplevels: Vec<ToplevelState>,
+    sessions: Vec<SessionState>,
+    frame: FrameState,
+}
+
+impl WlOutputsHandler for ExtState {
+    fn outputs(&mut self) -> &mut Vec<WlOutputInfo> {
+        &mut self.outputs
+    }
+}
+
+impl Dispatch<WlRegistry, GlobalListContents> for ExtState {
+    fn event(
+        _state: &mut Self,
+        _registry: &WlRegistry,
+        _event: wl_registry::Event,
+        _data: &GlobalListContents,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+    }
+}
+
+impl Dispatch<ExtForeignToplevelListV1, ()> for ExtState {
+    fn event(
+        state: &mut Self,
+        _list: &ExtForeignToplevelListV1,
+        event: ext_foreign_toplevel_list_v1::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+        if let ext_foreign_toplevel_list_v1::Event::Toplevel { toplevel } = event {
+            state.toplevels.push(ToplevelState {
+                handle: toplevel,
+                identifier: String::new(),
+                title: String::new(),
+                app_id: String::new(),
+                is_closed: false,
+            });
+        }
+    }
+
+    event_created_child!(ExtState, ExtForeignToplevelListV1, [
+        ext_foreign_toplevel_list_v1::EVT_TOPLEVEL_OPCODE => (ExtForeignToplevelHandleV1, ()),
+    ]);
+}
+
+impl Dispatch<ExtForeignToplevelHandleV1, ()> for ExtState {
+    fn event(
+        state: &mut Self,
+        handle: &ExtForeignToplevelHandleV1,
+        event: ext_foreign_toplevel_handle_v1::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+        let Some(toplevel) = state
+            .toplevels
+            .iter_mut()
+            .find(|toplevel| &toplevel.handle == handle)
+        else {
+            return;
+        };
+
+        match event {
+            ext_foreign_toplevel_handle_v1::Event::Identifier { identifier } => {
+                toplevel.identifier = identifier
+            }
+            ext_foreign_toplevel_handle_v1::Event::Title { title } => toplevel.title = title,
+            ext_foreign_toplevel_handle_v1::Event::AppId { app_id } => toplevel.app_id = app_id,
+            ext_foreign_toplevel_handle_v1::Event::Closed => toplevel.is_closed = true,
+            _ => {}
+        }
+    }
+}
+
+impl Dispatch<ExtImageCopyCaptureSessionV1, usize> for ExtState {
+    fn event(
+        state: &mut Self,
+        _session: &ExtImageCopyCaptureSessionV1,
+        event: ext_image_copy_capture_session_v1::Event,
+        index: &usize,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+        let Some(session_state) = state.sessions.get_mut(*index) else {
+            return;
+        };
+
+        match event {
+            ext_image_copy_capture_session_v1::Event::BufferSize { width, height } => {
+                session_state.buffer_size = Some((width, height));
+            }
+            // 每种支持的格式发送一次，使用第一个能转换的格式
+            ext_image_copy_capture_session_v1::Event::ShmFormat {
+                format: WEnum::Value(format),
+            } if session_state.shm_format.is_none() && ShmFormat::is_supported(format) => {
+                session_state.shm_format = Some(format);
+            }
+            ext_image_copy_capture_session_v1::Event::Done => session_state.is_done = true,
+            ext_image_copy_capture_session_v1::Event::Stopped => session_state.is_stopped = true,
+            _ => {}
+        }
+    }
+}
+
+impl Dispatch<ExtImageCopyCaptureFrameV1, ()> for ExtState {
+    fn event(
+        state: &mut Self,
+        _frame: &ExtImageCopyCaptureFrameV1,
+        event: ext_image_copy_capture_frame_v1::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+        match event {
+            ext_image_copy_capture_frame_v1::Event::Transform {
+                transform: WEnum::Value(transform),
+            } => state.frame.transform = Some(transform),
+            ext_image_copy_capture_frame_v1::Event::Ready => state.frame.is_ready = true,
+            ext_image_copy_capture_frame_v1::Event::Failed { reason } => {
+                state.frame.failure_reason = Some(reason)
+            }
+            _ => {}
+        }
+    }
+}
+
+delegate_dispatch!(ExtState: [WlOutput: usize] => WlOutputs);
+delegate_dispatch!(ExtState: [ZxdgOutputV1: usize] => WlOutputs);
+delegate_dispatch!(ExtState: [ZxdgOutputManagerV1: ()] => WlOutputs);
+delegate_noop!(ExtState: ignore WlShm);
+delegate_noop!(ExtState: WlShmPool);
+delegate_noop!(ExtState: ignore WlBuffer);
+delegate_noop!(ExtState: ExtImageCaptureSourceV1);
+delegate_noop!(ExtState: ExtOutputImageCaptureSourceManagerV1);
+delegate_noop!(ExtState: ExtForeignToplevelImageCaptureSourceManagerV1);
+delegate_noop!(ExtState: ExtImageCopyCaptureManagerV1);
+
+/// 每次截图都使用新的连接，toplevel handle 与输出只在这个连接上有效
+struct ExtConnection {
+    _conn: Connection,
+    globals: GlobalList,
+    event_queue: EventQueue<ExtState>,
+    state: ExtState,
+    shm: WlShm,
+    copy_manager: ExtImageCopyCaptureManagerV1,
+}
+
+impl ExtConnection {
+    fn new() -> XCapResult<ExtConnection> {
+        let conn = Connection::connect_to_env()?;
+        let (globals, mut event_queue) = registry_queue_init::<ExtState>(&conn)?;
+        let qh = event_queue.handle();
+
+        let shm = globals.bind(&qh, 1..=1, ())?;
+        let copy_manager = globals.bind(&qh, 1..=1, ())?;
+
+        let mut state = ExtState::default();
+        bind_outputs(&globals, &mut event_queue, &mut state)?;
+
+        Ok(ExtConnection {
+            _conn: conn,
+            globals,
+            event_queue,
+            state,
+            shm,
+            copy_manager,
+        })
+    }
+
+    fn dispatch_until(&mut self, done: impl Fn(&ExtState) -> bool) -> XCapResult<()> {
+        while !done(&self.state) {
+            self.event_queue.blocking_dispatch(&mut self.state)?;
+        }
+
+        Ok(())
+    }
+
+    /// 返回全部窗口的 handle，与 `state.toplevels` 的顺序一致
+    fn list_toplevels(&mut self) -> XCapResult<Vec<ExtForeignToplevelHandleV1>> {
+        let list: ExtForeignToplevelListV1 =
+            self.globals.bind(&self.event_queue.handle(), 1..=1, ())?;
+
+        // 绑定后合成器会立即发送全部窗口及其属性
+        self.event_queue.roundtrip(&mut self.state)?;
+        list.stop();
+
+        Ok(self
+            .state
+            .toplevels
+            .iter()
+            .map(|toplevel| toplevel.handle.clone())
+            .collect())
+    }
+
+    fn toplevel_source_manager(&self) -> XCapResult<ExtForeignToplevelImageCaptureSourceManagerV1> {
+        Ok(self.globals.bind(&self.event_queue.handle(), 1..=1, ())?)
+    }
+
+    fn output_source(&self, output: &WlOutput) -> XCapResult<ExtImageCaptureSourceV1> {
+        let qh = self.event_queue.handle();
+        let source_manager: ExtOutputImageCaptureSourceManagerV1 =
+            self.globals.bind(&qh, 1..=1, ())?;
+
+        Ok(source_manager.create_source(output, &qh, ()))
+    }
+
+    /// 会话的状态保存在 `state.sessions` 中，下标作为 user data
+    fn create_session(
+        &mut self,
+        source: &ExtImageCaptureSourceV1,
+        paint_cursors: bool,
+    ) -> (usize, ExtImageCopyCaptureSessionV1) {
+        let options = if paint_cursors {
+            Options::PaintCursors
+        } else {
+            Options::empty()
+        };
+
+        let index = self.state.sessions.len();
+        self.state.sessions.push(SessionState::default());
+
+        let session =
+            self.copy_manager
+                .create_session(source, options, &self.event_queue.handle(), index);
+
+        (index, session)
+    }
+
+    /// 按会话的 buffer 约束创建 shm buffer，截取一帧
+    fn capture_source(
+        &mut self,
+        source: &ExtImageCaptureSourceV1,
+        paint_cursors: bool,
+    ) -> XCapResult<Frame> {
+        let (index, session) = self.create_session(source, paint_cursors);
+        let result = self.capture_session(index, &session);
+        session.destroy();
+
+        result
+    }
+
+    fn capture_session(
+        &mut self,
+        index: usize,
+        session: &ExtImageCopyCaptureSessionV1,
+    ) -> XCapResult<Frame> {
+        self.dispatch_until(|state| {
+            let session_state = &state.sessions[index];
+            session_state.is_done || session_state.is_stopped
+        })?;
+
+        let session_state = &self.state.sessions[index];
+        if session_state.is_stopped {
+            return Err(XCapError::new("Image copy capture session stopped"));
+        }
+
+        let (Some((width, height)), Some(format)) =
+            (session_state.buffer_size, session_state.shm_format)
+        else {
+            return Err(XCapError::new(
+                "Image copy capture has no supported wl_shm format",
+            ));
+        };
+
+        let qh = self.event_queue.handle();
+        let shm_buffer = ShmBuffer::new(
+            &self.shm,
+            &qh,
+            ShmFormat {
+                format,
+                width,
+                height,
+                stride: width * 4,
+            },
+        )?;
+
+        self.state.frame = FrameState::default();
+        let frame = session.create_frame(&qh, ());
+        frame.attach_buffer(&shm_buffer.buffer);
+        frame.damage_buffer(0, 0, width as i32, height as i32);
+        frame.capture();
+
+        self.dispatch_until(|state| state.frame.is_ready || state.frame.failure_reason.is_some())?;
+        frame.destroy();
+
+        match self.state.frame.failure_reason {
+            None => shm_buffer.to_frame(
+                false,
+                self.state.frame.transform.unwrap_or(Transform::Normal),
+            ),
+            Some(WEnum::Value(FailureReason::Stopped)) => {
+                Err(XCapError::new("Image copy capture source is gone"))
+            }
+            Some(_) => Err(XCapError::new("Image copy capture failed")),
+        }
+    }
+}
+
+/// 列出全部顶层窗口，并通过截图会话的 buffer 约束得到窗口大小
+pub fn ext_toplevels() -> XCapResult<Vec<ExtToplevel>> {
+    let mut ext_conn = ExtConnection::new()?;
+    let handles = ext_conn.list_toplevels()?;
+
+    // 合成器不支持截取窗口时，只返回窗口列表
+    let mut sessions = Vec::with_capacity(handles.len());
+    if let Ok(source_manager) = ext_conn.toplevel_source_manager() {
+        let qh = ext_conn.event_queue.handle();
+
+        for handle in handles.iter() {
+            let source = source_manager.create_source(handle, &qh, ());
+            let (_, session) = ext_conn.create_session(&source, false);
+            sessions.push((source, session));
+        }
+
+        // 创建会话后合成器会立即发送 buffer 约束
+        ext_conn.event_queue.roundtrip(&mut ext_conn.state)?;
+    }
+
+    for (source, session) in sessions {
+        session.destroy();
+        source.destroy();
+    }
+
+    // 会话与窗口按相同的顺序创建，下标一一对应
+    let toplevels = ext_conn
+        .state
+        .toplevels
+        .iter()
+        .enumerate()
+        .filter(|(_, toplevel)| !toplevel.is_closed && !toplevel.identifier.is_empty())
+        .map(|(index, toplevel)| {
+            let (width, height) = ext_conn
+                .state
+                .sessions
+                .get(index)
+                .and_then(|session_state| session_state.buffer_size)
+                .unwrap_or((0, 0));
+
+            ExtToplevel {
+                identifier: toplevel.identifier.clone(),
+                title: toplevel.title.clone(),
+                app_id: toplevel.app_id.clone(),
+                width,
+                height,
+            }
+        })
+        .collect();
+
+    Ok(toplevels)
+}
+
+/// 截取 identifier 对应的窗口，窗口已关闭时返回 None
+pub fn ext_capture_toplevel(identifier: &str) -> XCapResult<Option<Frame>> {
+    let mut ext_conn = ExtConnection::new()?;
+    let handles = ext_conn.list_toplevels()?;
+
+    let Some(handle) = ext_conn
+        .state
+        .toplevels
+        .iter()
+        .zip(handles)
+        .find(|(toplevel, _)| !toplevel.is_closed && toplevel.identifier == identifier)
+        .map(|(_, handle)| handle)
+    else {
+        return Ok(None);
+    };
+
+    let source_manager = ext_conn.toplevel_source_manager()?;
+    let source = source_manager.create_source(&handle, &ext_conn.event_queue.handle(), ());
+    let result = ext_conn.capture_source(&source, false);
+    source.destroy();
+
+    result.map(Some)
+}
+
+/// 截取显示器上的区域，`monitor` 是 `Monitor` 的逻辑坐标，`region` 相对于显示器左上角
+pub fn ext_capture_output(
+    monitor_name: &str,
+    monitor: Rect,
+    region: Rect,
+    paint_cursors: bool,
+) -> XCapResult<Frame> {
+    let mut ext_conn = ExtConnection::new()?;
+
+    let output_info = find_output(&ext_conn.state.outputs, monitor_name, &monitor)
+        .ok_or_else(|| XCapError::new("Not found wayland output for monitor"))?
+        .clone();
+
+    let source = ext_conn.output_source(&output_info.output)?;
+    let result = ext_conn.capture_source(&source, paint_cursors);
+    source.destroy();
+    let frame = result?;
+
+    // 协议只能截取整个输出，按物理像素与逻辑坐标的比例裁剪
+    let output_region = to_output_region(&output_info, &monitor, &region);
+    let scale = frame.width as f32 / output_info.width.max(1) as f32;
+
+    let x = (output_region.x as f32 * scale) as u32;
+    let y = (output_region.y as f32 * scale) as u32;
+    let width = (output_region.width as f32 * scale) as u32;
+    let height = (output_region.height as f32 * scale) as u32;
+
+    frame.crop(x, y, width, height)
+}
@@ -0,0 +1,65 @@
+use image::RgbaImage;
+
+use crate::{
+    capture_options::{WindowCaptureMode, X11CaptureMethod},
+    error::{XCapError, XCapResult},
+    frame_buffer::FrameBuffer,
+    rect::Rect,
+};
+
+use super::{
+    capture::{capture_x11_monitor_region_into, capture_x11_window},
+    impl_monitor::ImplMonitor,
+    impl_window::ImplWindow,
+    xorg_connection::XorgConnection,
+};
+
+/// 持有一个 X 连接，获取显示器、窗口与截图都在这个连接上完成
+#[derive(Debug)]
+pub struct ImplCaptureSession {
+    conn: XorgConnection,
+}
+
+impl ImplCaptureSession {
+    pub fn new() -> XCapResult<ImplCaptureSession> {
+        Ok(ImplCaptureSession {
+            conn: XorgConnection::connect()?,
+        })
+    }
+
+    pub fn monitors(&self) -> XCapResult<Vec<ImplMonitor>> {
+        ImplMonitor::all_with_conn(&self.conn)
+    }
+
+    pub fn windows(&self) -> XCapResult<Vec<ImplWindow>> {
+        ImplWindow::all_with_conn(&self.conn)
+    }
+
+    pub fn last_capture_method(&self) -> Option<X11CaptureMethod> {
+        self.conn.last_capture_method()
+    }
+
+    pub fn capture_region_into(
+        &self,
+        impl_monitor: &ImplMonitor,
+        region: Rect,
+        frame_buffer: &mut FrameBuffer,
+    ) -> XCapResult<()> {
+        capture_x11_monitor_region_into(&self.conn, impl_monitor, region, frame_buffer)
+    }
+
+    pub fn capture_window(
+        &self,
+        impl_window: &ImplWindow,
+        mode: WindowCaptureMode,
+    ) -> XCapResult<RgbaImage> {
+        if impl_window.wayland_identifier.is_some() {
+            return Err(XCapError::Unsupported {
+                feature: "Capture session",
+                backend: "Wayland",
+            });
+        }
+
+        capture_x11_window(&self.conn, impl_window, mode)
+    }
+}
@@ -0,0 +1,114 @@
+use image::RgbaImage;
+use xcb::{xfixes, Connection, Extension};
+
+use crate::error::{XCapError, XCapResult};
+
+use super::impl_monitor::get_scale_factor;
+
+#[derive(Debug, Clone)]
+pub(crate) struct ImplCursor {
+    pub x: i32,
+    pub y: i32,
+    pub pixel_x: i32,
+    pub pixel_y: i32,
+    pub hotspot_x: u32,
+    pub hotspot_y: u32,
+    pub serial: u32,
+    pub image: RgbaImage,
+}
+
+// XFixes 返回预乘 alpha 的 ARGB，转换为非预乘的 RGBA
+fn argb_to_rgba_image(argb: &[u32], width: u32, height: u32) -> XCapResult<RgbaImage> {
+    let mut rgba = Vec::with_capacity((width * height * 4) as usize);
+
+    for &pixel in argb.iter().take((width * height) as usize) {
+        let a = (pixel >> 24) as u8;
+        let unpremultiply = |c: u32| -> u8 {
+            if a == 0 {
+                0
+            } else {
+                ((c & 0xff) * 255 / a as u32).min(255) as u8
+            }
+        };
+
+        rgba.push(unpremultiply(pixel >> 16));
+        rgba.push(unpremultiply(pixel >> 8));
+        rgba.push(unpremultiply(pixel));
+        rgba.push(a);
+    }
+
+    RgbaImage::from_raw(width, height, rgba)
+        .ok_or_else(|| XCapError::new("RgbaImage::from_raw failed"))
+}
+
+impl ImplCursor {
+    pub fn new() -> XCapResult<ImplCursor> {
+        let (conn, index) = Connection::connect_with_extensions(None, &[Extension::XFixes], &[])?;
+
+        let setup = conn.get_setup();
+        let screen = setup
+            .roots()
+            .nth(index as usize)
+            .ok_or_else(|| XCapError::new("Not found screen"))?;
+
+        let scale_factor = get_scale_factor(&conn, screen).unwrap_or(1.0);
+
+        // XFixes 要求先协商版本，GetCursorImage 需要 2.0 以上
+        let query_version_cookie = conn.send_request(&xfixes::QueryVersion {
+            client_major_version: 4,
+            client_minor_version: 0,
+        });
+        let query_version_reply = conn.wait_for_reply(query_version_cookie)?;
+        if query_version_reply.major_version() < 2 {
+            return Err(XCapError::Unsupported {
+                feature: "Cursor capture",
+                backend: "X11 without XFixes 2.0",
+            });
+        }
+
+        let get_cursor_image_cookie = conn.send_request(&xfixes::GetCursorImage {});
+        let get_cursor_image_reply = conn.wait_for_reply(get_cursor_image_cookie)?;
+
+        let width = get_cursor_image_reply.width() as u32;
+        let height = get_cursor_image_reply.height() as u32;
+        let pixel_x = get_cursor_image_reply.x() as i32;
+        let pixel_y = get_cursor_image_reply.y() as i32;
+
+        Ok(ImplCursor {
+            x: ((pixel_x as f32) / scale_factor) as i32,
+            y: ((pixel_y as f32) / scale_factor) as i32,
+            pixel_x,
+            pixel_y,
+            hotspot_x: get_cursor_image_reply.xhot() as u32,
+            hotspot_y: get_cursor_image_reply.yhot() as u32,
+            serial: get_cursor_image_reply.cursor_serial(),
+            image: argb_to_rgba_image(get_cursor_image_reply.cursor_image(), width, height)?,
+        })
+    }
+
+    /// 将光标绘制到以 (origin_x, origin_y) 为左上角的截图上，超出截图的部分被裁剪
+    pub fn composite(&self, rgba_image: &mut RgbaImage, origin_x: i32, origin_y: i32) {
+        let left = self.pixel_x - self.hotspot_x as i32 - origin_x;
+        let top = self.pixel_y - self.hotspot_y as i32 - origin_y;
+
+        for (cursor_x, cursor_y, src) in self.image.enumerate_pixels() {
+            let x = left + cursor_x as i32;
+            let y = top + cursor_y as i32;
+
+            if x < 0 || y < 0 || x >= rgba_image.width() as i32 || y >= rgba_image.height() as i32 {
+                continue;
+            }
+
+            let alpha = src[3] as u32;
+            if alpha == 0 {
+                continue;
+            }
+
+            let dst = rgba_image.get_pixel_mut(x as u32, y as u32);
+            for i in 0..3 {
+                dst[i] = ((src[i] as u32 * alpha + dst[i] as u32 * (255 - alpha)) / 255) as u8;
+            }
+            dst[3] = (alpha + dst[3] as u32 * (255 - alpha) / 255) as u8;
+        }
+    }
+}
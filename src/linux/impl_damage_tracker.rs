@@ -0,0 +1,342 @@
+use image::RgbaImage;
+use xcb::{
+    damage,
+    x::{Drawable, Window},
+    xfixes, Extension, Xid,
+};
+
+use crate::{damage_tracker::DamageRect, error::XCapResult, rect::Rect};
+
+use super::{
+    backend::require_x11, impl_monitor::ImplMonitor, impl_window::ImplWindow,
+    xorg_connection::XorgConnection,
+};
+
+pub(crate) struct ImplDamageTracker {
+    conn: XorgConnection,
+    window: Window,
+    damage: damage::Damage,
+    parts: xfixes::Region,
+    // 还没有通过 damaged_regions 返回、还没有重新截取的区域，两者分别清空
+    unreported: xfixes::Region,
+    uncaptured: xfixes::Region,
+    x: i32,
+    y: i32,
+    width: u32,
+    height: u32,
+    buffer: Option<RgbaImage>,
+}
+
+impl std::fmt::Debug for ImplDamageTracker {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ImplDamageTracker")
+            .field("window", &self.window)
+            .field("damage", &self.damage)
+            .field("x", &self.x)
+            .field("y", &self.y)
+            .field("width", &self.width)
+            .field("height", &self.height)
+            .finish()
+    }
+}
+
+impl ImplDamageTracker {
+    fn new(window: Window, x: i32, y: i32, width: u32, height: u32) -> XCapResult<Self> {
+        require_x11("Damage tracking")?;
+
+        let conn =
+            XorgConnection::connect_with_extensions(&[Extension::Damage, Extension::XFixes])?;
+
+        ImplDamageTracker::with_conn(conn, window, x, y, width, height)
+    }
+
+    fn with_conn(
+        conn: XorgConnection,
+        window: Window,
+        x: i32,
+        y: i32,
+        width: u32,
+        height: u32,
+    ) -> XCapResult<Self> {
+        // 使用扩展前需要先协商版本
+        let damage_version_cookie = conn.send_request(&damage::QueryVersion {
+            client_major_version: 1,
+            client_minor_version: 1,
+        });
+        let xfixes_version_cookie = conn.send_request(&xfixes::QueryVersion {
+            client_major_version: 4,
+            client_minor_version: 0,
+        });
+        conn.wait_for_reply(damage_version_cookie)?;
+        conn.wait_for_reply(xfixes_version_cookie)?;
+
+        let damage = conn.generate_id();
+        conn.send_and_check_request(&damage::Create {
+            damage,
+            drawable: Drawable::Window(window),
+            level: damage::ReportLevel::NonEmpty,
+        })?;
+
+        let [parts, unreported, uncaptured] = [(); 3].map(|_| conn.generate_id());
+        for region in [parts, unreported, uncaptured] {
+            conn.send_and_check_request(&xfixes::CreateRegion {
+                region,
+                rectangles: &[],
+            })?;
+        }
+
+        Ok(ImplDamageTracker {
+            conn,
+            window,
+            damage,
+            parts,
+            unreported,
+            uncaptured,
+            x,
+            y,
+            width,
+            height,
+            buffer: None,
+        })
+    }
+
+    pub fn from_monitor(impl_monitor: &ImplMonitor) -> XCapResult<ImplDamageTracker> {
+        let x = ((impl_monitor.x as f32) * impl_monitor.scale_factor) as i32;
+        let y = ((impl_monitor.y as f32) * impl_monitor.scale_factor) as i32;
+        let width = ((impl_monitor.width as f32) * impl_monitor.scale_factor) as u32;
+        let height = ((impl_monitor.height as f32) * impl_monitor.scale_factor) as u32;
+
+        ImplDamageTracker::new(impl_monitor.root()?, x, y, width, height)
+    }
+
+    pub fn from_window(impl_window: &ImplWindow) -> XCapResult<ImplDamageTracker> {
+        ImplDamageTracker::new(
+            impl_window.window,
+            0,
+            0,
+            impl_window.width,
+            impl_window.height,
+        )
+    }
+
+    /// 取出 X server 上累积的 damage，合并到 unreported 与 uncaptured 中
+    fn collect_damage(&self) -> XCapResult<()> {
+        // 丢弃已收到的 DamageNotify 事件，损坏区域统一通过 Subtract 获取
+        while self.conn.poll_for_event()?.is_some() {}
+
+        // repair 为 None 时清空 damage，并把清空前的区域写入 parts
+        self.conn.send_and_check_request(&damage::Subtract {
+            damage: self.damage,
+            repair: xfixes::Region::none(),
+            parts: self.parts,
+        })?;
+
+        // 由 X server 合并区域，只调用其中一个方法时也不会无限增长
+        for region in [self.unreported, self.uncaptured] {
+            self.conn.send_and_check_request(&xfixes::UnionRegion {
+                source1: region,
+                source2: self.parts,
+                destination: region,
+            })?;
+        }
+
+        Ok(())
+    }
+
+    /// 读取并清空 region，裁剪到跟踪的区域内
+    fn take_region(&self, region: xfixes::Region) -> XCapResult<Vec<DamageRect>> {
+        let fetch_region_cookie = self.conn.send_request(&xfixes::FetchRegion { region });
+        let fetch_region_reply = self.conn.wait_for_reply(fetch_region_cookie)?;
+
+        self.conn.send_and_check_request(&xfixes::SetRegion {
+            region,
+            rectangles: &[],
+        })?;
+
+        let left = self.x;
+        let top = self.y;
+        let right = self.x + self.width as i32;
+        let bottom = self.y + self.height as i32;
+
+        let damage_rects = fetch_region_reply
+            .rectangles()
+            .iter()
+            .filter_map(|rectangle| {
+                // 裁剪到跟踪的区域内，并转换为相对坐标
+                let x1 = (rectangle.x as i32).max(left);
+                let y1 = (rectangle.y as i32).max(top);
+                let x2 = (rectangle.x as i32 + rectangle.width as i32).min(right);
+                let y2 = (rectangle.y as i32 + rectangle.height as i32).min(bottom);
+
+                if x2 <= x1 || y2 <= y1 {
+                    return None;
+                }
+
+                Some(DamageRect {
+                    x: (x1 - left) as u32,
+                    y: (y1 - top) as u32,
+                    width: (x2 - x1) as u32,
+                    height: (y2 - y1) as u32,
+                })
+            })
+            .collect();
+
+        Ok(damage_rects)
+    }
+
+    pub fn damaged_regions(&mut self) -> XCapResult<Vec<DamageRect>> {
+        self.collect_damage()?;
+        self.take_region(self.unreported)
+    }
+
+    pub fn capture_image(&mut self) -> XCapResult<&RgbaImage> {
+        // 先清空 damage 再截图，截图期间产生的变化会在下次调用时拿到。
+        // 已经通过 damaged_regions 返回的区域仍然在 uncaptured 中，同样会重新截取
+        self.collect_damage()?;
+        let damage_rects = self.take_region(self.uncaptured)?;
+
+        // 第一次截取整个区域，之后只把变化的部分直接写入已有的图像
+        let (mut buffer, regions) = match self.buffer.take() {
+            Some(buffer) => {
+                let regions = damage_rects
+                    .iter()
+                    .map(|damage_rect| {
+                        Rect::new(
+                            damage_rect.x as i32,
+                            damage_rect.y as i32,
+                            damage_rect.width,
+                            damage_rect.height,
+                        )
+                    })
+                    .collect();
+
+                (buffer, regions)
+            }
+            None => (
+                RgbaImage::new(self.width, self.height),
+                vec![Rect::new(0, 0, self.width, self.height)],
+            ),
+        };
+
+        for region in regions {
+            self.conn.capture_into_image(
+                Drawable::Window(self.window),
+                self.x,
+                self.y,
+                region,
+                &mut buffer,
+            )?;
+        }
+
+        Ok(self.buffer.insert(buffer))
+    }
+}
+
+impl Drop for ImplDamageTracker {
+    fn drop(&mut self) {
+        self.conn.send_request(&damage::Destroy {
+            damage: self.damage,
+        });
+        for region in [self.parts, self.unreported, self.uncaptured] {
+            self.conn.send_request(&xfixes::DestroyRegion { region });
+        }
+        let _ = self.conn.flush();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::platform::test_server::start_xvfb;
+    use xcb::{
+        x::{ChangeGc, CreateGc, Gc, PolyFillRectangle, Rectangle},
+        Connection,
+    };
+
+    #[test]
+    #[ignore = "needs Xvfb"]
+    fn xvfb_capture_after_damaged_regions() {
+        let xvfb = start_xvfb(24);
+        let display = format!(":{}", xvfb.address);
+
+        let (conn, screen_num) = Connection::connect_with_extensions(
+            Some(&display),
+            &[Extension::Damage, Extension::XFixes],
+            &[],
+        )
+        .unwrap();
+        let conn = XorgConnection::new(conn, screen_num);
+        let screen = conn.get_setup().roots().nth(screen_num as usize).unwrap();
+        let (root, white_pixel, black_pixel) =
+            (screen.root(), screen.white_pixel(), screen.black_pixel());
+
+        // 用另一个连接绘制，模拟其它程序修改屏幕内容
+        let (draw_conn, _) = Connection::connect(Some(&display)).unwrap();
+        let gc = draw_conn.generate_id();
+        draw_conn
+            .send_and_check_request(&CreateGc {
+                cid: gc,
+                drawable: Drawable::Window(root),
+                value_list: &[Gc::Foreground(white_pixel)],
+            })
+            .unwrap();
+
+        let mut tracker = ImplDamageTracker::with_conn(conn, root, 0, 0, 64, 48).unwrap();
+        assert_eq!(
+            tracker.capture_image().unwrap().get_pixel(12, 12).0[..3],
+            [0, 0, 0]
+        );
+
+        draw_conn
+            .send_and_check_request(&PolyFillRectangle {
+                drawable: Drawable::Window(root),
+                gc,
+                rectangles: &[Rectangle {
+                    x: 8,
+                    y: 8,
+                    width: 8,
+                    height: 8,
+                }],
+            })
+            .unwrap();
+
+        let damage_rect = DamageRect {
+            x: 8,
+            y: 8,
+            width: 8,
+            height: 8,
+        };
+        assert_eq!(tracker.damaged_regions().unwrap(), vec![damage_rect]);
+        assert_eq!(tracker.damaged_regions().unwrap(), vec![]);
+
+        // damaged_regions 已经返回过的区域仍然会被重新截取
+        let rgba_image = tracker.capture_image().unwrap();
+        assert_eq!(rgba_image.get_pixel(12, 12).0, [255, 255, 255, 255]);
+        assert_eq!(rgba_image.get_pixel(20, 20).0[..3], [0, 0, 0]);
+
+        // capture_image 不影响 damaged_regions
+        draw_conn
+            .send_and_check_request(&ChangeGc {
+                gc,
+                value_list: &[Gc::Foreground(black_pixel)],
+            })
+            .unwrap();
+        draw_conn
+            .send_and_check_request(&PolyFillRectangle {
+                drawable: Drawable::Window(root),
+                gc,
+                rectangles: &[Rectangle {
+                    x: 8,
+                    y: 8,
+                    width: 8,
+                    height: 8,
+                }],
+            })
+            .unwrap();
+        assert_eq!(
+            tracker.capture_image().unwrap().get_pixel(12, 12).0[..3],
+            [0, 0, 0]
+        );
+        assert_eq!(tracker.damaged_regions().unwrap(), vec![damage_rect]);
+    }
+}
@@ -1,23 +1,38 @@
 use image::RgbaImage;
-use std::str;
+use std::{str, sync::mpsc::Receiver};
 use xcb::{
     randr::{
         GetCrtcInfo, GetMonitors, GetOutputInfo, GetScreenResources, Mode, ModeFlag, ModeInfo,
         MonitorInfo, MonitorInfoBuf, Output, Rotation,
     },
-    x::{GetProperty, Screen, ScreenBuf, ATOM_RESOURCE_MANAGER, ATOM_STRING, CURRENT_TIME},
+    x::{GetProperty, Screen, ScreenBuf, Window, ATOM_RESOURCE_MANAGER, ATOM_STRING, CURRENT_TIME},
     Connection, Xid,
 };
 
-use crate::error::{XCapError, XCapResult};
+use crate::{
+    capture_options::CaptureOptions,
+    error::{XCapError, XCapResult},
+    frame::Frame,
+    frame_buffer::FrameBuffer,
+    video_recorder::VideoRecorder,
+};
 
-use super::capture::capture_monitor;
+use super::{
+    backend::wayland_detect,
+    capture::{
+        capture_monitor, capture_monitor_frame, capture_monitor_into, capture_monitor_region,
+        capture_monitor_with_options, monitor_video_recorder,
+    },
+    wayland_outputs::{wl_outputs, WlOutputInfo},
+    xorg_connection::XorgConnection,
+};
 
 #[derive(Debug, Clone)]
 pub(crate) struct ImplMonitor {
-    pub screen_buf: ScreenBuf,
+    /// 没有 XWayland 时从 wl_output 获取的显示器为 None
+    pub screen_buf: Option<ScreenBuf>,
     #[allow(unused)]
-    pub monitor_info_buf: MonitorInfoBuf,
+    pub monitor_info_buf: Option<MonitorInfoBuf>,
     pub id: u32,
     pub name: String,
     pub x: i32,
@@ -55,7 +70,7 @@ fn get_current_frequency(mode_infos: &[ModeInfo], mode: Mode) -> f32 {
     }
 }
 
-fn get_scale_factor(conn: &Connection, screen: &Screen) -> XCapResult<f32> {
+pub(super) fn get_scale_factor(conn: &Connection, screen: &Screen) -> XCapResult<f32> {
     let xft_dpi_prefix = "Xft.dpi:\t";
 
     let get_property_cookie = conn.send_request(&GetProperty {
@@ -134,8 +149,8 @@ impl ImplMonitor {
         let get_output_info_reply = conn.wait_for_reply(get_output_info_cookie)?;
 
         Ok(ImplMonitor {
-            screen_buf: screen.to_owned(),
-            monitor_info_buf: monitor_info.to_owned(),
+            screen_buf: Some(screen.to_owned()),
+            monitor_info_buf: Some(monitor_info.to_owned()),
             id: output.resource_id(),
             name: str::from_utf8(get_output_info_reply.name())?.to_string(),
             x: ((monitor_info.x() as f32) / scale_factor) as i32,
@@ -149,17 +164,57 @@ impl ImplMonitor {
         })
     }
 
+    fn from_wl_output(output_info: &WlOutputInfo, is_primary: bool) -> ImplMonitor {
+        ImplMonitor {
+            screen_buf: None,
+            monitor_info_buf: None,
+            id: output_info.id,
+            name: output_info.name.clone(),
+            x: output_info.x,
+            y: output_info.y,
+            width: output_info.width as u32,
+            height: output_info.height as u32,
+            rotation: output_info.rotation(),
+            scale_factor: output_info.scale_factor(),
+            frequency: output_info.refresh as f32 / 1000.0,
+            is_primary,
+        }
+    }
+
     pub fn all() -> XCapResult<Vec<ImplMonitor>> {
-        let (conn, index) = Connection::connect(None)?;
+        // 没有 XWayland 的 Wayland 会话中，从 wl_output 获取显示器
+        match ImplMonitor::all_x11() {
+            Err(XCapError::NoDisplay(_)) if wayland_detect() => ImplMonitor::all_wayland(),
+            result => result,
+        }
+    }
+
+    fn all_wayland() -> XCapResult<Vec<ImplMonitor>> {
+        // Wayland 没有主显示器的概念，把第一个输出当作主显示器
+        let impl_monitors = wl_outputs()?
+            .iter()
+            .enumerate()
+            .map(|(index, output_info)| ImplMonitor::from_wl_output(output_info, index == 0))
+            .collect();
+
+        Ok(impl_monitors)
+    }
+
+    fn all_x11() -> XCapResult<Vec<ImplMonitor>> {
+        let conn = XorgConnection::connect()?;
+
+        ImplMonitor::all_with_conn(&conn)
+    }
 
+    pub fn all_with_conn(conn: &XorgConnection) -> XCapResult<Vec<ImplMonitor>> {
         let setup = conn.get_setup();
 
         let screen = setup
             .roots()
-            .nth(index as usize)
+            .nth(conn.screen_num() as usize)
             .ok_or_else(|| XCapError::new("Not found screen"))?;
 
-        let scale_factor = get_scale_factor(&conn, screen).unwrap_or(1.0);
+        let scale_factor = get_scale_factor(conn, screen).unwrap_or(1.0);
 
         let get_monitors_cookie = conn.send_request(&GetMonitors {
             window: screen.root(),
@@ -187,10 +242,10 @@ impl ImplMonitor {
             };
 
             let (rotation, frequency) =
-                get_rotation_frequency(&conn, mode_infos, output).unwrap_or((0.0, 0.0));
+                get_rotation_frequency(conn, mode_infos, output).unwrap_or((0.0, 0.0));
 
             if let Ok(impl_monitor) = ImplMonitor::new(
-                &conn,
+                conn,
                 screen,
                 monitor_info,
                 output,
@@ -226,14 +281,49 @@ impl ImplMonitor {
                     && y >= impl_monitor.y
                     && y < impl_monitor.y + impl_monitor.height as i32
             })
-            .ok_or_else(|| XCapError::new("Get screen info failed"))?;
+            .ok_or_else(|| XCapError::NoDisplay(format!("No monitor at ({}, {})", x, y)))?;
 
         Ok(impl_monitor.clone())
     }
 }
 
 impl ImplMonitor {
+    /// X11 的 root window，Wayland 输出上没有
+    pub fn root(&self) -> XCapResult<Window> {
+        self.screen_buf
+            .as_ref()
+            .map(|screen_buf| screen_buf.root())
+            .ok_or(XCapError::Unsupported {
+                feature: "X11 capture",
+                backend: "Wayland",
+            })
+    }
+
     pub fn capture_image(&self) -> XCapResult<RgbaImage> {
         capture_monitor(self)
     }
+
+    pub fn capture_image_with_options(&self, options: &CaptureOptions) -> XCapResult<RgbaImage> {
+        capture_monitor_with_options(self, options)
+    }
+
+    pub fn capture_region(&self, x: u32, y: u32, width: u32, height: u32) -> XCapResult<RgbaImage> {
+        capture_monitor_region(self, x, y, width, height)
+    }
+
+    pub fn capture_into(&self, frame_buffer: &mut FrameBuffer) -> XCapResult<()> {
+        capture_monitor_into(self, frame_buffer)
+    }
+
+    pub fn capture_frame(&self) -> XCapResult<Frame> {
+        capture_monitor_frame(self)
+    }
+
+    pub fn capture_image_bgra_data(&self) -> XCapResult<Vec<u8>> {
+        capture_monitor_frame(self)?.into_bgra_data()
+    }
+
+    pub fn video_recorder(&self) -> XCapResult<(VideoRecorder, Receiver<XCapResult<Frame>>)> {
+        monitor_video_recorder(self)
+    }
 }
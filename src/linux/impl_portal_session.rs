@@ -0,0 +1,23 @@
+use crate::error::XCapResult;
+
+use super::pipewire_capture::{screencast_restore_token, start_screencast, stop_screencast};
+
+/// 会话本身保存在 pipewire_capture 中，截图时共用，这里只是它的句柄
+#[derive(Debug, Clone)]
+pub struct ImplPortalSession;
+
+impl ImplPortalSession {
+    pub fn start(restore_token: Option<&str>) -> XCapResult<ImplPortalSession> {
+        start_screencast(restore_token)?;
+
+        Ok(ImplPortalSession)
+    }
+
+    pub fn restore_token(&self) -> XCapResult<Option<String>> {
+        screencast_restore_token()
+    }
+
+    pub fn close(&self) -> XCapResult<()> {
+        stop_screencast()
+    }
+}
@@ -1,17 +1,32 @@
 use image::RgbaImage;
-use std::str;
+use std::{str, sync::mpsc::Receiver};
 use xcb::{
+    res,
     x::{
-        Atom, Drawable, GetGeometry, GetProperty, GetPropertyReply, InternAtom, QueryPointer,
-        TranslateCoordinates, Window, ATOM_ATOM, ATOM_NONE, ATOM_STRING, ATOM_WM_CLASS,
-        ATOM_WM_NAME,
+        Atom, Drawable, GetGeometry, GetProperty, GetPropertyReply, QueryPointer,
+        TranslateCoordinates, Window, ATOM_ANY, ATOM_ATOM, ATOM_CARDINAL, ATOM_STRING, ATOM_WINDOW,
+        ATOM_WM_CLASS, ATOM_WM_NAME,
     },
-    Connection, Xid,
+    Connection, Extension, Xid,
 };
 
-use crate::error::{XCapError, XCapResult};
+use crate::{
+    backend::Backend,
+    capture_options::WindowCaptureMode,
+    error::{XCapError, XCapResult},
+    frame::Frame,
+    rect::{FrameExtents, Rect},
+    video_recorder::{VideoRecorder, WindowRecorderEvent},
+};
 
-use super::{capture::capture_window, impl_monitor::ImplMonitor, utils::Rect};
+use super::{
+    backend::capture_backends,
+    capture::{capture_window, capture_window_frame, window_video_recorder},
+    ext_capture::{ext_toplevels, ExtToplevel},
+    impl_monitor::ImplMonitor,
+    utils::{decode_compound_text, decode_latin1},
+    xorg_connection::XorgConnection,
+};
 
 #[derive(Debug, Clone)]
 pub(crate) struct ImplWindow {
@@ -19,6 +34,7 @@ pub(crate) struct ImplWindow {
     pub id: u32,
     pub title: String,
     pub app_name: String,
+    pub process_id: u32,
     pub current_monitor: ImplMonitor,
     pub x: i32,
     pub y: i32,
@@ -26,21 +42,11 @@ pub(crate) struct ImplWindow {
     pub height: u32,
     pub is_minimized: bool,
     pub is_maximized: bool,
-}
-
-fn get_atom(conn: &Connection, name: &str) -> XCapResult<Atom> {
-    let atom_cookie = conn.send_request(&InternAtom {
-        only_if_exists: true,
-        name: name.as_bytes(),
-    });
-    let atom_reply = conn.wait_for_reply(atom_cookie)?;
-    let atom = atom_reply.atom();
-
-    if atom == ATOM_NONE {
-        return Err(XCapError::new(format!("{} not supported", name)));
-    }
-
-    Ok(atom)
+    pub z_index: i32,
+    pub is_focused: bool,
+    pub frame_extents: FrameExtents,
+    /// ext-foreign-toplevel-list 的窗口标识，X11 窗口为 None
+    pub wayland_identifier: Option<String>,
 }
 
 fn get_window_property(
@@ -65,23 +71,148 @@ fn get_window_property(
     Ok(window_property_reply)
 }
 
+fn decode_text_property(conn: &XorgConnection, reply: &GetPropertyReply) -> String {
+    if reply.format() != 8 {
+        return String::new();
+    }
+
+    let bytes = reply.value::<u8>();
+    let r#type = reply.r#type();
+
+    if r#type == ATOM_STRING {
+        decode_latin1(bytes)
+    } else if conn.atom("COMPOUND_TEXT").is_ok_and(|atom| atom == r#type) {
+        decode_compound_text(bytes)
+    } else {
+        // UTF8_STRING 以及其它未知类型都按 UTF-8 处理
+        String::from_utf8_lossy(bytes).to_string()
+    }
+}
+
+fn get_window_title(conn: &XorgConnection, window: Window) -> XCapResult<String> {
+    // 优先使用 EWMH 的 _NET_WM_NAME（UTF8_STRING），再回退到 ICCCM 的 WM_NAME
+    // https://specifications.freedesktop.org/wm-spec/1.3/ar01s05.html#id-1.6.2
+    if let Ok(net_wm_name_atom) = conn.atom("_NET_WM_NAME") {
+        let net_wm_name_reply =
+            get_window_property(conn, window, net_wm_name_atom, ATOM_ANY, 0, 1024)?;
+        let title = decode_text_property(conn, &net_wm_name_reply);
+
+        if !title.is_empty() {
+            return Ok(title);
+        }
+    }
+
+    let wm_name_reply = get_window_property(conn, window, ATOM_WM_NAME, ATOM_ANY, 0, 1024)?;
+
+    Ok(decode_text_property(conn, &wm_name_reply))
+}
+
+fn get_window_pid(conn: &XorgConnection, window: Window) -> XCapResult<u32> {
+    // _NET_WM_PID 由客户端自己设置，不一定存在
+    if let Ok(wm_pid_atom) = conn.atom("_NET_WM_PID") {
+        let wm_pid_reply = get_window_property(conn, window, wm_pid_atom, ATOM_CARDINAL, 0, 1)?;
+
+        if wm_pid_reply.format() == 32 {
+            if let Some(&pid) = wm_pid_reply.value::<u32>().first() {
+                return Ok(pid);
+            }
+        }
+    }
+
+    // 回退到 XRes，由 X server 查询本机客户端连接的 pid
+    let is_res_active = conn
+        .active_extensions()
+        .any(|extension| extension == Extension::Res);
+
+    if !is_res_active {
+        return Err(XCapError::new("XRes extension not available"));
+    }
+
+    let query_client_ids_cookie = conn.send_request(&res::QueryClientIds {
+        specs: &[res::ClientIdSpec {
+            client: window.resource_id(),
+            mask: res::ClientIdMask::LOCAL_CLIENT_PID,
+        }],
+    });
+    let query_client_ids_reply = conn.wait_for_reply(query_client_ids_cookie)?;
+
+    let pid = query_client_ids_reply
+        .ids()
+        .find(|id| id.spec().mask.contains(res::ClientIdMask::LOCAL_CLIENT_PID))
+        .and_then(|id| id.value().first().copied())
+        .ok_or_else(|| XCapError::new("Get window pid failed"))?;
+
+    Ok(pid)
+}
+
+fn get_cardinal_extents(conn: &XorgConnection, window: Window, name: &str) -> Option<[i32; 4]> {
+    let atom = conn.atom(name).ok()?;
+    let extents_reply = get_window_property(conn, window, atom, ATOM_CARDINAL, 0, 4).ok()?;
+
+    if extents_reply.format() != 32 {
+        return None;
+    }
+
+    match extents_reply.value::<u32>() {
+        &[left, right, top, bottom] => Some([left as i32, right as i32, top as i32, bottom as i32]),
+        _ => None,
+    }
+}
+
+fn get_frame_extents(conn: &XorgConnection, window: Window) -> FrameExtents {
+    // _NET_FRAME_EXTENTS 是窗口管理器在客户区外添加的装饰（标题栏、边框）
+    // https://specifications.freedesktop.org/wm-spec/1.3/ar01s05.html#id-1.6.13
+    let [left, right, top, bottom] =
+        get_cardinal_extents(conn, window, "_NET_FRAME_EXTENTS").unwrap_or_default();
+    // _GTK_FRAME_EXTENTS 是客户端装饰在窗口内部绘制的阴影区域，用负值表示
+    let [shadow_left, shadow_right, shadow_top, shadow_bottom] =
+        get_cardinal_extents(conn, window, "_GTK_FRAME_EXTENTS").unwrap_or_default();
+
+    FrameExtents {
+        left: left - shadow_left,
+        right: right - shadow_right,
+        top: top - shadow_top,
+        bottom: bottom - shadow_bottom,
+    }
+}
+
+/// root window 上 _NET_ACTIVE_WINDOW 记录的焦点窗口
+pub(super) fn get_active_window(
+    conn: &Connection,
+    root_window: Window,
+    atom: Atom,
+) -> Option<Window> {
+    get_window_list(conn, root_window, atom)
+        .ok()?
+        .first()
+        .copied()
+}
+
+fn get_window_list(conn: &Connection, window: Window, property: Atom) -> XCapResult<Vec<Window>> {
+    let window_list_reply = get_window_property(conn, window, property, ATOM_WINDOW, 0, 4096)?;
+
+    if window_list_reply.format() != 32 {
+        return Err(XCapError::new("Get window list failed"));
+    }
+
+    Ok(window_list_reply.value::<Window>().to_vec())
+}
+
 impl ImplWindow {
     fn new(
-        conn: &Connection,
+        conn: &XorgConnection,
         window: &Window,
         impl_monitors: &Vec<ImplMonitor>,
+        z_index: i32,
+        is_focused: bool,
     ) -> XCapResult<ImplWindow> {
-        let title = {
-            let get_title_reply =
-                get_window_property(conn, *window, ATOM_WM_NAME, ATOM_STRING, 0, 1024)?;
-            str::from_utf8(get_title_reply.value())?.to_string()
-        };
+        let title = get_window_title(conn, *window)?;
 
         let app_name = {
             let get_class_reply =
                 get_window_property(conn, *window, ATOM_WM_CLASS, ATOM_STRING, 0, 1024)?;
 
-            let class = str::from_utf8(get_class_reply.value())?;
+            let class = decode_latin1(get_class_reply.value());
 
             class
                 .split('\u{0}')
@@ -90,6 +221,10 @@ impl ImplWindow {
                 .to_string()
         };
 
+        let process_id = get_window_pid(conn, *window).unwrap_or(0);
+
+        let frame_extents = get_frame_extents(conn, *window);
+
         let (x, y, width, height) = {
             let get_geometry_cookie = conn.send_request(&GetGeometry {
                 drawable: Drawable::Window(*window),
@@ -116,7 +251,7 @@ impl ImplWindow {
             let mut max_area = 0;
             let mut find_result = impl_monitors
                 .first()
-                .ok_or(XCapError::new("Get screen info failed"))?;
+                .ok_or_else(|| XCapError::NoDisplay("No monitor found".to_string()))?;
 
             let window_rect = Rect::new(x, y, width, height);
 
@@ -130,7 +265,9 @@ impl ImplWindow {
                 );
 
                 // 获取最大的面积
-                let area = window_rect.overlap_area(monitor_rect);
+                let area = window_rect
+                    .intersection(&monitor_rect)
+                    .map_or(0, |rect| rect.width * rect.height);
                 if area > max_area {
                     max_area = area;
                     find_result = impl_monitor;
@@ -142,10 +279,10 @@ impl ImplWindow {
 
         let (is_minimized, is_maximized) = {
             // https://specifications.freedesktop.org/wm-spec/1.3/ar01s05.html
-            let wm_state_atom = get_atom(conn, "_NET_WM_STATE")?;
-            let wm_state_hidden_atom = get_atom(conn, "_NET_WM_STATE_HIDDEN")?;
-            let wm_state_maximized_vert_atom = get_atom(conn, "_NET_WM_STATE_MAXIMIZED_VERT")?;
-            let wm_state_maximized_horz_atom = get_atom(conn, "_NET_WM_STATE_MAXIMIZED_HORZ")?;
+            let wm_state_atom = conn.atom("_NET_WM_STATE")?;
+            let wm_state_hidden_atom = conn.atom("_NET_WM_STATE_HIDDEN")?;
+            let wm_state_maximized_vert_atom = conn.atom("_NET_WM_STATE_MAXIMIZED_VERT")?;
+            let wm_state_maximized_horz_atom = conn.atom("_NET_WM_STATE_MAXIMIZED_HORZ")?;
 
             let wm_state_reply =
                 get_window_property(conn, *window, wm_state_atom, ATOM_ATOM, 0, 12)?;
@@ -172,6 +309,7 @@ impl ImplWindow {
             id: window.resource_id(),
             title,
             app_name,
+            process_id,
             current_monitor,
             x,
             y,
@@ -179,18 +317,91 @@ impl ImplWindow {
             height,
             is_minimized,
             is_maximized,
+            z_index,
+            is_focused,
+            frame_extents,
+            wayland_identifier: None,
         })
     }
 
+    /// Wayland 不提供窗口的位置、层级与焦点，这些字段使用默认值
+    fn from_ext_toplevel(ext_toplevel: ExtToplevel, current_monitor: ImplMonitor) -> ImplWindow {
+        // identifier 是字符串，用 FNV-1a 哈希得到稳定的 id
+        let id = ext_toplevel
+            .identifier
+            .bytes()
+            .fold(0x811c9dc5u32, |hash, byte| {
+                (hash ^ byte as u32).wrapping_mul(0x01000193)
+            });
+
+        // ext-foreign-toplevel-list 只提供标题、app_id 与大小，其它字段都是占位值，
+        // 在 Window 的文档中说明
+        ImplWindow {
+            window: Window::none(),
+            id,
+            title: ext_toplevel.title,
+            app_name: ext_toplevel.app_id,
+            process_id: 0,
+            current_monitor,
+            x: 0,
+            y: 0,
+            width: ext_toplevel.width,
+            height: ext_toplevel.height,
+            is_minimized: false,
+            is_maximized: false,
+            z_index: 0,
+            is_focused: false,
+            frame_extents: FrameExtents::default(),
+            wayland_identifier: Some(ext_toplevel.identifier),
+        }
+    }
+
     pub fn all() -> XCapResult<Vec<ImplWindow>> {
-        let (conn, _) = Connection::connect(None)?;
+        // XWayland 中的窗口仍然可以通过 X11 枚举，只有首选 ext-image-copy-capture 时才使用 Wayland
+        match capture_backends(None, "Window enumeration", |c| c.window_enumeration) {
+            Ok(backends) if backends[0] == Backend::ExtImageCopyCapture => {
+                ImplWindow::all_wayland()
+            }
+            _ => ImplWindow::all_x11(),
+        }
+    }
+
+    fn all_wayland() -> XCapResult<Vec<ImplWindow>> {
+        let current_monitor = ImplMonitor::all()?
+            .into_iter()
+            .next()
+            .ok_or_else(|| XCapError::NoDisplay("No monitor found".to_string()))?;
+
+        let impl_windows = ext_toplevels()?
+            .into_iter()
+            .map(|ext_toplevel| {
+                ImplWindow::from_ext_toplevel(ext_toplevel, current_monitor.clone())
+            })
+            .collect();
+
+        Ok(impl_windows)
+    }
+
+    fn all_x11() -> XCapResult<Vec<ImplWindow>> {
+        let conn = XorgConnection::connect()?;
+
+        ImplWindow::all_with_conn(&conn)
+    }
+
+    /// 显示器与窗口使用同一个连接获取
+    pub fn all_with_conn(conn: &XorgConnection) -> XCapResult<Vec<ImplWindow>> {
         let setup = conn.get_setup();
 
         // https://github.com/rust-x-bindings/rust-xcb/blob/main/examples/get_all_windows.rs
-        let client_list_atom = get_atom(&conn, "_NET_CLIENT_LIST")?;
+        // _NET_CLIENT_LIST_STACKING 按从底到顶的顺序排列，不支持时回退到 _NET_CLIENT_LIST
+        let client_list_atoms = [
+            conn.atom("_NET_CLIENT_LIST_STACKING"),
+            conn.atom("_NET_CLIENT_LIST"),
+        ];
+        let active_window_atom = conn.atom("_NET_ACTIVE_WINDOW").ok();
 
         let mut impl_windows = Vec::new();
-        let impl_monitors = ImplMonitor::all()?;
+        let impl_monitors = ImplMonitor::all_with_conn(conn)?;
 
         for screen in setup.roots() {
             let root_window = screen.root();
@@ -204,20 +415,23 @@ impl ImplWindow {
             };
 
             if query_pointer_reply.same_screen() {
-                let list_window_reply = match get_window_property(
-                    &conn,
-                    root_window,
-                    client_list_atom,
-                    ATOM_NONE,
-                    0,
-                    100,
-                ) {
-                    Ok(list_window_reply) => list_window_reply,
-                    _ => continue,
+                let Some(clients) = client_list_atoms.iter().find_map(|atom| {
+                    let atom = atom.as_ref().ok()?;
+                    get_window_list(conn, root_window, *atom).ok()
+                }) else {
+                    continue;
                 };
 
-                for client in list_window_reply.value::<Window>() {
-                    if let Ok(impl_window) = ImplWindow::new(&conn, client, &impl_monitors) {
+                let active_window =
+                    active_window_atom.and_then(|atom| get_active_window(conn, root_window, atom));
+
+                // 从顶到底返回，与 Windows、macOS 的枚举顺序保持一致
+                for (z_index, client) in clients.iter().enumerate().rev() {
+                    let is_focused = active_window == Some(*client);
+
+                    if let Ok(impl_window) =
+                        ImplWindow::new(conn, client, &impl_monitors, z_index as i32, is_focused)
+                    {
                         impl_windows.push(impl_window);
                     } else {
                         log::error!(
@@ -236,6 +450,70 @@ impl ImplWindow {
 
 impl ImplWindow {
     pub fn capture_image(&self) -> XCapResult<RgbaImage> {
-        capture_window(self)
+        capture_window(self, WindowCaptureMode::Client)
+    }
+
+    pub fn capture_image_with_mode(&self, mode: WindowCaptureMode) -> XCapResult<RgbaImage> {
+        capture_window(self, mode)
+    }
+
+    pub fn capture_frame(&self) -> XCapResult<Frame> {
+        capture_window_frame(self)
+    }
+
+    pub fn capture_image_bgra_data(&self) -> XCapResult<Vec<u8>> {
+        capture_window_frame(self)?.into_bgra_data()
+    }
+
+    pub fn video_recorder(&self) -> XCapResult<(VideoRecorder, Receiver<WindowRecorderEvent>)> {
+        window_video_recorder(self)
+    }
+
+    pub fn visible_region(&self) -> XCapResult<Vec<Rect>> {
+        if self.wayland_identifier.is_some() {
+            return Err(XCapError::Unsupported {
+                feature: "Window visible region",
+                backend: Backend::ExtImageCopyCapture.name(),
+            });
+        }
+
+        // 重新获取窗口列表，拿到最新的层级和位置
+        let impl_windows = ImplWindow::all()?;
+        let current = impl_windows
+            .iter()
+            .find(|impl_window| impl_window.window == self.window)
+            .ok_or(XCapError::WindowGone(self.id))?;
+
+        if current.is_minimized {
+            return Ok(Vec::new());
+        }
+
+        let mut region = vec![Rect::new(
+            current.x,
+            current.y,
+            current.width,
+            current.height,
+        )];
+
+        for impl_window in impl_windows.iter() {
+            if impl_window.z_index <= current.z_index || impl_window.is_minimized {
+                continue;
+            }
+
+            // 上层窗口的标题栏和边框同样会遮挡
+            let above_rect = impl_window.frame_extents.frame_rect(&Rect::new(
+                impl_window.x,
+                impl_window.y,
+                impl_window.width,
+                impl_window.height,
+            ));
+
+            region = region
+                .iter()
+                .flat_map(|rect| rect.subtract(&above_rect))
+                .collect();
+        }
+
+        Ok(region)
     }
 }
@@ -0,0 +1,452 @@
+use dbus::{
+    arg::{OwnedFd, PropMap, RefArg, Variant},
+    blocking::Connection,
+};
+use std::{collections::HashMap, fs::File, io::Read, os::fd::FromRawFd, thread, time::Duration};
+
+use crate::{
+    error::{XCapError, XCapResult},
+    frame::{Frame, PixelFormat},
+};
+
+// QImage::Format 的取值
+// https://doc.qt.io/qt-6/qimage.html#Format-enum
+const FORMAT_RGB32: u32 = 4;
+const FORMAT_ARGB32: u32 = 5;
+const FORMAT_ARGB32_PREMULTIPLIED: u32 = 6;
+const FORMAT_RGBX8888: u32 = 16;
+const FORMAT_RGBA8888: u32 = 17;
+const FORMAT_RGBA8888_PREMULTIPLIED: u32 = 18;
+
+/// ScreenShot2 返回的图像信息，像素数据写入调用方传入的 pipe
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct KWinImageInfo {
+    width: u32,
+    height: u32,
+    stride: u32,
+    format: u32,
+}
+
+impl KWinImageInfo {
+    fn from_results(results: &PropMap) -> XCapResult<KWinImageInfo> {
+        let get = |key: &str| {
+            results
+                .get(key)
+                .and_then(|value| value.as_u64())
+                .map(|value| value as u32)
+                .ok_or_else(|| XCapError::new(format!("KWin screenshot has no {}", key)))
+        };
+
+        if let Some(image_type) = results.get("type").and_then(|value| value.as_str()) {
+            if image_type != "raw" {
+                return Err(XCapError::new(format!(
+                    "Unsupported KWin screenshot type {}",
+                    image_type
+                )));
+            }
+        }
+
+        Ok(KWinImageInfo {
+            width: get("width")?,
+            height: get("height")?,
+            stride: get("stride")?,
+            format: get("format")?,
+        })
+    }
+}
+
+/// 按 QImage 的格式把 pipe 中读到的数据包装为 Frame，预乘 alpha 的颜色会先还原
+fn to_frame(data: Vec<u8>, info: KWinImageInfo) -> XCapResult<Frame> {
+    let KWinImageInfo {
+        width,
+        height,
+        stride,
+        format,
+    } = info;
+
+    // QImage::Format_RGB32 等格式按 0xAARRGGBB 存储，小端序下内存中是 B G R A
+    let (pixel_format, is_premultiplied) = match format {
+        FORMAT_RGB32 => (PixelFormat::Bgrx8, false),
+        FORMAT_ARGB32 => (PixelFormat::Bgra8, false),
+        FORMAT_ARGB32_PREMULTIPLIED => (PixelFormat::Bgra8, true),
+        FORMAT_RGBX8888 => (PixelFormat::Rgbx8, false),
+        FORMAT_RGBA8888 => (PixelFormat::Rgba8, false),
+        FORMAT_RGBA8888_PREMULTIPLIED => (PixelFormat::Rgba8, true),
+        _ => {
+            return Err(XCapError::new(format!(
+                "Unsupported KWin image format {}",
+                format
+            )))
+        }
+    };
+
+    let mut frame = Frame::from_raw(width, height, stride, pixel_format, data)?;
+
+    if is_premultiplied {
+        let row_len = width as usize * 4;
+        for row in frame.data.chunks_mut(stride as usize).take(height as usize) {
+            for pixel in row[..row_len].chunks_exact_mut(4) {
+                let a = pixel[3] as u32;
+                if a == 0 || a == 255 {
+                    continue;
+                }
+
+                for value in &mut pixel[..3] {
+                    *value = ((*value as u32 * 255 + a / 2) / a).min(255) as u8;
+                }
+            }
+        }
+    }
+
+    Ok(frame)
+}
+
+/// 调用 ScreenShot2 的方法，KWin 在回复后把像素数据写入 pipe 的写端
+/// https://invent.kde.org/plasma/kwin/-/blob/master/src/plugins/screenshot/org.kde.KWin.ScreenShot2.xml
+fn kwin_screenshot<A>(
+    conn: &Connection,
+    method: &str,
+    args: impl FnOnce(OwnedFd) -> A,
+) -> XCapResult<Frame>
+where
+    A: dbus::arg::AppendAll,
+{
+    let proxy = conn.with_proxy(
+        "org.kde.KWin",
+        "/org/kde/KWin/ScreenShot2",
+        Duration::from_secs(10),
+    );
+
+    let (read_fd, write_fd) = unsafe {
+        let mut fds = [0; 2];
+        if libc::pipe2(fds.as_mut_ptr(), libc::O_CLOEXEC) == -1 {
+            return Err(std::io::Error::last_os_error().into());
+        }
+
+        (File::from_raw_fd(fds[0]), OwnedFd::from_raw_fd(fds[1]))
+    };
+
+    // 数据可能超过 pipe 的缓冲区，在另一个线程中读取，避免 KWin 写入时阻塞
+    let reader = thread::spawn(move || {
+        let mut read_fd = read_fd;
+        let mut data = Vec::new();
+        read_fd.read_to_end(&mut data).map(|_| data)
+    });
+
+    // 写端随参数一起发送，调用结束后本进程中的写端会被关闭，读取才能结束
+    let result = proxy.method_call::<(PropMap,), _, _, _>(
+        "org.kde.KWin.ScreenShot2",
+        method,
+        args(write_fd),
+    );
+
+    // 调用失败时 KWin 可能不会关闭写端，不等待读取线程，直接返回错误
+    let (results,) = result?;
+    let data = reader
+        .join()
+        .map_err(|_| XCapError::new("Read KWin screenshot failed"))??;
+
+    to_frame(data, KWinImageInfo::from_results(&results)?)
+}
+
+fn options(entries: &[(&str, bool)]) -> PropMap {
+    let mut options: PropMap = HashMap::new();
+    for &(key, value) in entries {
+        options.insert(key.to_string(), Variant(Box::new(value)));
+    }
+
+    options
+}
+
+fn org_kde_kwin_capture_screen(
+    conn: &Connection,
+    name: &str,
+    include_cursor: bool,
+) -> XCapResult<Frame> {
+    let options = options(&[
+        ("include-cursor", include_cursor),
+        ("native-resolution", true),
+    ]);
+
+    kwin_screenshot(conn, "CaptureScreen", |pipe| (name, options, pipe))
+}
+
+fn org_kde_kwin_capture_area(
+    conn: &Connection,
+    x: i32,
+    y: i32,
+    width: u32,
+    height: u32,
+    include_cursor: bool,
+) -> XCapResult<Frame> {
+    let options = options(&[
+        ("include-cursor", include_cursor),
+        ("native-resolution", true),
+    ]);
+
+    kwin_screenshot(conn, "CaptureArea", |pipe| {
+        (x, y, width, height, options, pipe)
+    })
+}
+
+fn org_kde_kwin_capture_window(
+    conn: &Connection,
+    handle: &str,
+    include_decoration: bool,
+) -> XCapResult<Frame> {
+    let options = options(&[
+        ("include-decoration", include_decoration),
+        ("native-resolution", true),
+    ]);
+
+    kwin_screenshot(conn, "CaptureWindow", |pipe| (handle, options, pipe))
+}
+
+fn org_kde_kwin_capture_active_window(
+    conn: &Connection,
+    include_decoration: bool,
+) -> XCapResult<Frame> {
+    let options = options(&[
+        ("include-decoration", include_decoration),
+        ("native-resolution", true),
+    ]);
+
+    kwin_screenshot(conn, "CaptureActiveWindow", |pipe| (options, pipe))
+}
+
+/// 截取整个显示器，`name` 是显示器的输出名，例如 DP-1
+pub fn kwin_capture_screen(name: &str, include_cursor: bool) -> XCapResult<Frame> {
+    let conn = Connection::new_session()?;
+
+    org_kde_kwin_capture_screen(&conn, name, include_cursor)
+}
+
+/// 截取全局逻辑坐标中的区域
+pub fn kwin_capture_area(
+    x: i32,
+    y: i32,
+    width: u32,
+    height: u32,
+    include_cursor: bool,
+) -> XCapResult<Frame> {
+    let conn = Connection::new_session()?;
+
+    org_kde_kwin_capture_area(&conn, x, y, width, height, include_cursor)
+}
+
+/// 截取 KWin 内部 id 对应的窗口
+pub fn kwin_capture_window(handle: &str, include_decoration: bool) -> XCapResult<Frame> {
+    let conn = Connection::new_session()?;
+
+    org_kde_kwin_capture_window(&conn, handle, include_decoration)
+}
+
+/// 截取当前获得焦点的窗口
+pub fn kwin_capture_active_window(include_decoration: bool) -> XCapResult<Frame> {
+    let conn = Connection::new_session()?;
+
+    org_kde_kwin_capture_active_window(&conn, include_decoration)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::platform::test_server::PrivateBus;
+    use dbus::{
+        arg::messageitem::MessageItem,
+        channel::{Channel, MatchingReceiver, Sender},
+        message::MatchRule,
+        Message,
+    };
+    use std::{
+        io::Write,
+        sync::mpsc::{self, Receiver},
+    };
+
+    /// 模拟 KWin：回复图像信息后把 `data` 写入 pipe，收到的方法名与除 pipe 外的参数通过 channel 返回
+    fn start_mock_kwin(
+        bus: &PrivateBus,
+        info: KWinImageInfo,
+        data: Vec<u8>,
+    ) -> Receiver<(Option<String>, Vec<MessageItem>)> {
+        let address = bus.address().to_string();
+        let (ready_sender, ready_receiver) = mpsc::channel();
+        let (call_sender, call_receiver) = mpsc::channel();
+
+        thread::spawn(move || {
+            let mut channel = Channel::open_private(&address).unwrap();
+            channel.register().unwrap();
+            let conn = Connection::from(channel);
+            conn.request_name("org.kde.KWin", false, true, false)
+                .unwrap();
+
+            conn.start_receive(
+                MatchRule::new_method_call(),
+                Box::new(move |message: Message, conn: &Connection| {
+                    if message.member().as_deref() == Some("CaptureWindow") {
+                        let reply = message.error(
+                            &"org.kde.KWin.ScreenShot2.Error.NoAuthorized".into(),
+                            c"The process is not authorized to take a screenshot",
+                        );
+                        let _ = conn.send(reply);
+                        return true;
+                    }
+
+                    // pipe 是最后一个参数
+                    let mut iter = message.iter_init();
+                    let mut pipe = None;
+                    loop {
+                        if let Some(fd) = iter.get::<OwnedFd>() {
+                            pipe = Some(fd);
+                        }
+                        if !iter.next() {
+                            break;
+                        }
+                    }
+
+                    let mut results: PropMap = HashMap::new();
+                    results.insert("type".to_string(), Variant(Box::new("raw".to_string())));
+                    results.insert("width".to_string(), Variant(Box::new(info.width)));
+                    results.insert("height".to_string(), Variant(Box::new(info.height)));
+                    results.insert("stride".to_string(), Variant(Box::new(info.stride)));
+                    results.insert("format".to_string(), Variant(Box::new(info.format)));
+                    let _ = conn.send(message.method_return().append1(results));
+
+                    if let Some(fd) = pipe {
+                        let mut file = unsafe { File::from_raw_fd(fd.into_fd()) };
+                        file.write_all(&data).unwrap();
+                    }
+
+                    // 消息中还持有 pipe 的写端，不能留在进程里，否则读取端收不到 EOF
+                    let member = message.member().map(|member| member.to_string());
+                    let items = message
+                        .get_items()
+                        .into_iter()
+                        .filter(|item| !matches!(item, MessageItem::UnixFd(_)))
+                        .collect();
+                    let _ = call_sender.send((member, items));
+                    true
+                }),
+            );
+
+            ready_sender.send(()).unwrap();
+            while conn.process(Duration::from_millis(100)).is_ok() {}
+        });
+
+        ready_receiver.recv().unwrap();
+        call_receiver
+    }
+
+    #[test]
+    fn capture_area_from_mock_kwin() {
+        let Some(bus) = PrivateBus::start() else {
+            eprintln!("dbus-daemon not found, skipped");
+            return;
+        };
+
+        // 2x2 的 ARGB32 预乘图像，每行末尾有 4 字节填充
+        let info = KWinImageInfo {
+            width: 2,
+            height: 2,
+            stride: 12,
+            format: FORMAT_ARGB32_PREMULTIPLIED,
+        };
+        #[rustfmt::skip]
+        let data = vec![
+            0, 0, 255, 255,   0, 255, 0, 255,   0, 0, 0, 0,
+            255, 0, 0, 255,   0, 0, 64, 128,    0, 0, 0, 0,
+        ];
+        let calls = start_mock_kwin(&bus, info, data);
+
+        let conn = bus.connect();
+        let frame = org_kde_kwin_capture_area(&conn, 10, 20, 2, 2, true).unwrap();
+        assert_eq!(frame.pixel_format, PixelFormat::Bgra8);
+        let rgba_image = frame.into_rgba_image().unwrap();
+
+        assert_eq!(rgba_image.dimensions(), (2, 2));
+        assert_eq!(rgba_image.get_pixel(0, 0).0, [255, 0, 0, 255]);
+        assert_eq!(rgba_image.get_pixel(1, 0).0, [0, 255, 0, 255]);
+        assert_eq!(rgba_image.get_pixel(0, 1).0, [0, 0, 255, 255]);
+        assert_eq!(rgba_image.get_pixel(1, 1).0, [128, 0, 0, 128]);
+
+        let (member, items) = calls.recv().unwrap();
+        assert_eq!(member.as_deref(), Some("CaptureArea"));
+        assert_eq!(
+            items[..4],
+            [
+                MessageItem::Int32(10),
+                MessageItem::Int32(20),
+                MessageItem::UInt32(2),
+                MessageItem::UInt32(2),
+            ]
+        );
+        let MessageItem::Dict(options) = &items[4] else {
+            panic!("options is not a dict: {:?}", items[4]);
+        };
+        assert!(options.iter().any(|(key, value)| {
+            key == &MessageItem::Str("include-cursor".to_string())
+                && value == &MessageItem::Variant(Box::new(MessageItem::Bool(true)))
+        }));
+    }
+
+    #[test]
+    fn capture_screen_from_mock_kwin() {
+        let Some(bus) = PrivateBus::start() else {
+            eprintln!("dbus-daemon not found, skipped");
+            return;
+        };
+
+        let info = KWinImageInfo {
+            width: 1,
+            height: 1,
+            stride: 4,
+            format: FORMAT_RGBX8888,
+        };
+        let calls = start_mock_kwin(&bus, info, vec![1, 2, 3, 0]);
+
+        let conn = bus.connect();
+        let frame = org_kde_kwin_capture_screen(&conn, "DP-1", false).unwrap();
+        assert_eq!(frame.pixel_format, PixelFormat::Rgbx8);
+        let rgba_image = frame.into_rgba_image().unwrap();
+
+        assert_eq!(rgba_image.get_pixel(0, 0).0, [1, 2, 3, 255]);
+
+        let (member, items) = calls.recv().unwrap();
+        assert_eq!(member.as_deref(), Some("CaptureScreen"));
+        assert_eq!(items[0], MessageItem::Str("DP-1".to_string()));
+    }
+
+    #[test]
+    fn unauthorized_is_permission_denied() {
+        let Some(bus) = PrivateBus::start() else {
+            eprintln!("dbus-daemon not found, skipped");
+            return;
+        };
+
+        let info = KWinImageInfo {
+            width: 1,
+            height: 1,
+            stride: 4,
+            format: FORMAT_RGB32,
+        };
+        let _calls = start_mock_kwin(&bus, info, vec![0; 4]);
+
+        let conn = bus.connect();
+        let result =
+            org_kde_kwin_capture_window(&conn, "{00000000-0000-0000-0000-000000000000}", true);
+
+        assert!(matches!(result, Err(XCapError::PermissionDenied(_))));
+    }
+
+    #[test]
+    fn incomplete_data_is_an_error() {
+        let info = KWinImageInfo {
+            width: 2,
+            height: 2,
+            stride: 8,
+            format: FORMAT_RGB32,
+        };
+
+        assert!(to_frame(vec![0; 12], info).is_err());
+    }
+}
@@ -1,7 +1,26 @@
-mod capture;
+pub mod backend;
+pub mod capture;
+mod composite_capture;
+mod ext_capture;
+mod kwin_capture;
+#[cfg(feature = "pipewire")]
+mod pipewire_capture;
+mod portal;
+#[cfg(test)]
+mod test_server;
 mod utils;
 mod wayland_capture;
+mod wayland_outputs;
+mod wayland_shm;
+mod wlr_capture;
 mod xorg_capture;
+mod xorg_connection;
+mod xorg_convert;
 
+pub mod impl_capture_session;
+pub mod impl_cursor;
+pub mod impl_damage_tracker;
 pub mod impl_monitor;
+#[cfg(feature = "pipewire")]
+pub mod impl_portal_session;
 pub mod impl_window;
@@ -0,0 +1,765 @@
+use dbus::{
+    arg::{OwnedFd as DbusOwnedFd, PropMap, RefArg, Variant},
+    blocking::Connection,
+    Path,
+};
+use pipewire::{self as pw, spa};
+use std::{
+    collections::HashMap,
+    io::Cursor,
+    os::fd::{FromRawFd, OwnedFd},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc, Arc, Condvar, Mutex,
+    },
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+use crate::{
+    error::{XCapError, XCapResult},
+    frame::{Frame, PixelFormat},
+    rect::Rect,
+};
+
+use super::portal::{
+    parse_restore_token, parse_streams, portal_proxy, portal_request, unique_token, StreamInfo,
+};
+
+const SCREENCAST_INTERFACE: &str = "org.freedesktop.portal.ScreenCast";
+// SelectSources 的 types 参数，1 表示显示器
+const SOURCE_TYPE_MONITOR: u32 = 1;
+// SelectSources 的 persist_mode 参数，1 表示授权在程序运行期间有效，2 表示直到用户撤销
+const PERSIST_MODE_TRANSIENT: u32 = 1;
+const PERSIST_MODE_PERSISTENT: u32 = 2;
+
+#[derive(Debug, Default)]
+struct StreamFrames {
+    // 最近一帧的原始数据，按需再转换，避免每帧都做格式转换
+    latest: Mutex<Option<Frame>>,
+    condvar: Condvar,
+    is_broken: AtomicBool,
+}
+
+impl StreamFrames {
+    fn set_broken(&self) {
+        self.is_broken.store(true, Ordering::Release);
+        // 持有锁再通知，避免等待帧的线程错过唤醒
+        let _guard = self.latest.lock();
+        self.condvar.notify_all();
+    }
+
+    fn is_broken(&self) -> bool {
+        self.is_broken.load(Ordering::Acquire)
+    }
+
+    fn latest_frame(&self, timeout: Duration) -> XCapResult<Frame> {
+        let latest = self
+            .latest
+            .lock()
+            .map_err(|_| XCapError::new("Get frame lock failed"))?;
+
+        let (latest, _) = self
+            .condvar
+            .wait_timeout_while(latest, timeout, |latest| {
+                latest.is_none() && !self.is_broken()
+            })
+            .map_err(|_| XCapError::new("Get frame lock failed"))?;
+
+        match latest.as_ref() {
+            Some(frame) => Ok(frame.clone()),
+            None if self.is_broken() => Err(XCapError::new("PipeWire stream closed")),
+            None => Err(XCapError::Timeout(String::from(
+                "Waiting for the first PipeWire frame",
+            ))),
+        }
+    }
+}
+
+/// 建立 ScreenCast 会话，返回会话路径、视频流以及下次恢复授权用的 restore_token
+fn start_portal_session(
+    conn: &Connection,
+    persist_mode: u32,
+    restore_token: Option<&str>,
+) -> XCapResult<(Path<'static>, Vec<StreamInfo>, Option<String>)> {
+    let proxy = portal_proxy(conn);
+
+    let session_results = portal_request(conn, Duration::from_secs(10), |handle_token| {
+        let mut options: PropMap = HashMap::new();
+        options.insert(
+            String::from("handle_token"),
+            Variant(Box::new(handle_token.to_string())),
+        );
+        options.insert(
+            String::from("session_handle_token"),
+            Variant(Box::new(unique_token())),
+        );
+
+        proxy.method_call::<(Path<'static>,), _, _, _>(
+            SCREENCAST_INTERFACE,
+            "CreateSession",
+            (options,),
+        )?;
+
+        Ok(())
+    })?;
+
+    let session_handle = session_results
+        .get("session_handle")
+        .and_then(|session_handle| session_handle.as_str())
+        .map(|session_handle| Path::from(session_handle.to_string()))
+        .ok_or_else(|| XCapError::new("Get screencast session handle failed"))?;
+
+    portal_request(conn, Duration::from_secs(10), |handle_token| {
+        let mut options: PropMap = HashMap::new();
+        options.insert(
+            String::from("handle_token"),
+            Variant(Box::new(handle_token.to_string())),
+        );
+        options.insert(
+            String::from("types"),
+            Variant(Box::new(SOURCE_TYPE_MONITOR)),
+        );
+        options.insert(String::from("multiple"), Variant(Box::new(true)));
+        // 旧版本的门户（ScreenCast version < 4）会忽略这两个参数
+        options.insert(
+            String::from("persist_mode"),
+            Variant(Box::new(persist_mode)),
+        );
+        if let Some(restore_token) = restore_token {
+            options.insert(
+                String::from("restore_token"),
+                Variant(Box::new(restore_token.to_string())),
+            );
+        }
+
+        proxy.method_call::<(Path<'static>,), _, _, _>(
+            SCREENCAST_INTERFACE,
+            "SelectSources",
+            (session_handle.clone(), options),
+        )?;
+
+        Ok(())
+    })?;
+
+    // Start 会弹出选择对话框，等待用户操作；restore_token 有效时不会弹出
+    let start_results = portal_request(conn, Duration::from_secs(60), |handle_token| {
+        let mut options: PropMap = HashMap::new();
+        options.insert(
+            String::from("handle_token"),
+            Variant(Box::new(handle_token.to_string())),
+        );
+
+        proxy.method_call::<(Path<'static>,), _, _, _>(
+            SCREENCAST_INTERFACE,
+            "Start",
+            (session_handle.clone(), "", options),
+        )?;
+
+        Ok(())
+    })?;
+
+    let streams = parse_streams(&start_results);
+    if streams.is_empty() {
+        return Err(XCapError::new("Screencast returned no streams"));
+    }
+
+    Ok((session_handle, streams, parse_restore_token(&start_results)))
+}
+
+fn open_pipewire_remote(conn: &Connection, session_handle: &Path<'static>) -> XCapResult<OwnedFd> {
+    let (fd,): (DbusOwnedFd,) = portal_proxy(conn).method_call(
+        SCREENCAST_INTERFACE,
+        "OpenPipeWireRemote",
+        (session_handle.clone(), PropMap::new()),
+    )?;
+
+    // 文件描述符的所有权转移给 OwnedFd
+    Ok(unsafe { OwnedFd::from_raw_fd(fd.into_fd()) })
+}
+
+fn video_format_param() -> XCapResult<Vec<u8>> {
+    // 不声明 modifier，合成器会使用可映射的共享内存 buffer 而不是 DMA-BUF
+    let format = spa::pod::object!(
+        spa::utils::SpaTypes::ObjectParamFormat,
+        spa::param::ParamType::EnumFormat,
+        spa::pod::property!(
+            spa::param::format::FormatProperties::MediaType,
+            Id,
+            spa::param::format::MediaType::Video
+        ),
+        spa::pod::property!(
+            spa::param::format::FormatProperties::MediaSubtype,
+            Id,
+            spa::param::format::MediaSubtype::Raw
+        ),
+        spa::pod::property!(
+            spa::param::format::FormatProperties::VideoFormat,
+            Choice,
+            Enum,
+            Id,
+            spa::param::video::VideoFormat::BGRx,
+            spa::param::video::VideoFormat::BGRx,
+            spa::param::video::VideoFormat::BGRA,
+            spa::param::video::VideoFormat::RGBx,
+            spa::param::video::VideoFormat::RGBA,
+        ),
+        spa::pod::property!(
+            spa::param::format::FormatProperties::VideoSize,
+            Choice,
+            Range,
+            Rectangle,
+            spa::utils::Rectangle {
+                width: 1920,
+                height: 1080
+            },
+            spa::utils::Rectangle {
+                width: 1,
+                height: 1
+            },
+            spa::utils::Rectangle {
+                width: 8192,
+                height: 8192
+            }
+        ),
+        spa::pod::property!(
+            spa::param::format::FormatProperties::VideoFramerate,
+            Choice,
+            Range,
+            Fraction,
+            spa::utils::Fraction { num: 30, denom: 1 },
+            spa::utils::Fraction { num: 0, denom: 1 },
+            spa::utils::Fraction { num: 360, denom: 1 }
+        ),
+    );
+
+    let (cursor, _) = spa::pod::serialize::PodSerializer::serialize(
+        Cursor::new(Vec::new()),
+        &spa::pod::Value::Object(format),
+    )
+    .map_err(|err| XCapError::new(format!("Serialize video format failed: {:?}", err)))?;
+
+    Ok(cursor.into_inner())
+}
+
+fn copy_buffer(
+    buffer: &mut pw::buffer::Buffer,
+    format: &spa::param::video::VideoInfoRaw,
+) -> Option<Frame> {
+    let data = buffer.datas_mut().first_mut()?;
+    let chunk = data.chunk();
+    let offset = chunk.offset() as usize;
+    let size = chunk.size() as usize;
+    let stride = chunk.stride();
+
+    let width = format.size().width;
+    let height = format.size().height;
+    let stride = if stride > 0 { stride as u32 } else { width * 4 };
+
+    let bytes = data.data()?.get(offset..offset + size)?;
+
+    let video_format = format.format();
+    let pixel_format = [
+        (spa::param::video::VideoFormat::BGRx, PixelFormat::Bgrx8),
+        (spa::param::video::VideoFormat::BGRA, PixelFormat::Bgra8),
+        (spa::param::video::VideoFormat::RGBx, PixelFormat::Rgbx8),
+        (spa::param::video::VideoFormat::RGBA, PixelFormat::Rgba8),
+    ]
+    .into_iter()
+    .find(|&(format, _)| format == video_format)
+    .map(|(_, pixel_format)| pixel_format)?;
+
+    Frame::from_raw(width, height, stride, pixel_format, bytes.to_vec()).ok()
+}
+
+type PipewireStream = (
+    pw::stream::Stream,
+    pw::stream::StreamListener<spa::param::video::VideoInfoRaw>,
+);
+
+fn connect_stream(
+    core: &pw::core::Core,
+    node_id: u32,
+    frames: Arc<StreamFrames>,
+) -> XCapResult<PipewireStream> {
+    let stream = pw::stream::Stream::new(
+        core,
+        "xcap",
+        pw::properties::properties! {
+            *pw::keys::MEDIA_TYPE => "Video",
+            *pw::keys::MEDIA_CATEGORY => "Capture",
+            *pw::keys::MEDIA_ROLE => "Screen",
+        },
+    )?;
+
+    let state_frames = frames.clone();
+    let listener = stream
+        .add_local_listener_with_user_data(spa::param::video::VideoInfoRaw::default())
+        .state_changed(move |_, _, _, state| {
+            if matches!(
+                state,
+                pw::stream::StreamState::Error(_) | pw::stream::StreamState::Unconnected
+            ) {
+                state_frames.set_broken();
+            }
+        })
+        .param_changed(|_, format, id, param| {
+            let Some(param) = param else {
+                return;
+            };
+            if id != spa::param::ParamType::Format.as_raw() {
+                return;
+            }
+
+            if let Err(err) = format.parse(param) {
+                log::error!("Parse PipeWire video format failed: {:?}", err);
+            }
+        })
+        .process(move |stream, format| {
+            let Some(mut buffer) = stream.dequeue_buffer() else {
+                return;
+            };
+
+            if let (Some(raw_frame), Ok(mut latest)) =
+                (copy_buffer(&mut buffer, format), frames.latest.lock())
+            {
+                *latest = Some(raw_frame);
+                frames.condvar.notify_all();
+            }
+        })
+        .register()?;
+
+    let format_param = video_format_param()?;
+    let mut params = [spa::pod::Pod::from_bytes(&format_param)
+        .ok_or_else(|| XCapError::new("Invalid video format param"))?];
+
+    stream.connect(
+        spa::utils::Direction::Input,
+        Some(node_id),
+        pw::stream::StreamFlags::AUTOCONNECT | pw::stream::StreamFlags::MAP_BUFFERS,
+        &mut params,
+    )?;
+
+    Ok((stream, listener))
+}
+
+/// PipeWire 的对象都不能跨线程，全部在这个线程里创建和销毁
+fn run_pipewire(
+    fd: OwnedFd,
+    streams: Vec<(u32, Arc<StreamFrames>)>,
+    quit_receiver: pw::channel::Receiver<()>,
+    ready_sender: mpsc::Sender<XCapResult<()>>,
+) {
+    let result = (|| -> XCapResult<()> {
+        let mainloop = pw::main_loop::MainLoop::new(None)?;
+        let context = pw::context::Context::new(&mainloop)?;
+        let core = context.connect_fd(fd, None)?;
+
+        let weak_mainloop = mainloop.downgrade();
+        let _quit_receiver = quit_receiver.attach(mainloop.loop_(), move |_| {
+            if let Some(mainloop) = weak_mainloop.upgrade() {
+                mainloop.quit();
+            }
+        });
+
+        let mut pipewire_streams = Vec::with_capacity(streams.len());
+        for (node_id, frames) in streams.iter() {
+            pipewire_streams.push(connect_stream(&core, *node_id, frames.clone())?);
+        }
+
+        let _ = ready_sender.send(Ok(()));
+        mainloop.run();
+
+        Ok(())
+    })();
+
+    for (_, frames) in streams.iter() {
+        frames.set_broken();
+    }
+
+    if let Err(err) = result {
+        let _ = ready_sender.send(Err(err));
+    }
+}
+
+/// 一个 ScreenCast 会话，只在建立时弹出一次授权对话框，之后的截图都从视频流中读取
+struct ScreenCast {
+    // D-Bus 连接断开时门户会关闭会话，需要一直持有
+    conn: Connection,
+    session_handle: Path<'static>,
+    persist_mode: u32,
+    restore_token: Option<String>,
+    streams: Vec<(StreamInfo, Arc<StreamFrames>)>,
+    quit_sender: pw::channel::Sender<()>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl ScreenCast {
+    fn start(persist_mode: u32, restore_token: Option<&str>) -> XCapResult<ScreenCast> {
+        let conn = Connection::new_session()?;
+        let (session_handle, stream_infos, restore_token) =
+            start_portal_session(&conn, persist_mode, restore_token)?;
+        let fd = open_pipewire_remote(&conn, &session_handle)?;
+
+        let streams: Vec<(StreamInfo, Arc<StreamFrames>)> = stream_infos
+            .into_iter()
+            .map(|stream_info| (stream_info, Arc::new(StreamFrames::default())))
+            .collect();
+        let thread_streams = streams
+            .iter()
+            .map(|(stream_info, frames)| (stream_info.node_id, frames.clone()))
+            .collect();
+
+        let (quit_sender, quit_receiver) = pw::channel::channel();
+        let (ready_sender, ready_receiver) = mpsc::channel();
+        let thread =
+            thread::spawn(move || run_pipewire(fd, thread_streams, quit_receiver, ready_sender));
+
+        let screencast = ScreenCast {
+            conn,
+            session_handle,
+            persist_mode,
+            restore_token,
+            streams,
+            quit_sender,
+            thread: Some(thread),
+        };
+
+        ready_receiver
+            .recv()
+            .map_err(|_| XCapError::new("PipeWire thread exited"))??;
+
+        Ok(screencast)
+    }
+
+    fn is_broken(&self) -> bool {
+        self.thread
+            .as_ref()
+            .is_none_or(|thread| thread.is_finished())
+            || self.streams.iter().any(|(_, frames)| frames.is_broken())
+    }
+
+    /// 按逻辑坐标匹配显示器对应的视频流，只有一路流时直接使用
+    fn find_stream(&self, monitor: &Rect) -> Option<&(StreamInfo, Arc<StreamFrames>)> {
+        if let [stream] = self.streams.as_slice() {
+            return Some(stream);
+        }
+
+        self.streams
+            .iter()
+            .find(|(stream_info, _)| stream_info.position == Some((monitor.x, monitor.y)))
+            .or_else(|| {
+                self.streams.iter().find(|(stream_info, _)| {
+                    stream_info.size == Some((monitor.width as i32, monitor.height as i32))
+                })
+            })
+    }
+}
+
+impl Drop for ScreenCast {
+    fn drop(&mut self) {
+        let _ = self.quit_sender.send(());
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+
+        let proxy = self.conn.with_proxy(
+            "org.freedesktop.portal.Desktop",
+            self.session_handle.clone(),
+            Duration::from_secs(1),
+        );
+        if let Err(err) =
+            proxy.method_call::<(), _, _, _>("org.freedesktop.portal.Session", "Close", ())
+        {
+            log::debug!("Close screencast session failed: {}", err);
+        }
+    }
+}
+
+static SCREENCAST: Mutex<Option<ScreenCast>> = Mutex::new(None);
+
+/// 替换当前的会话，返回新的 restore_token
+pub fn start_screencast(restore_token: Option<&str>) -> XCapResult<Option<String>> {
+    let mut screencast = SCREENCAST
+        .lock()
+        .map_err(|_| XCapError::new("Get screencast lock failed"))?;
+
+    // 先关闭旧会话，门户同一时间只给一个会话使用 restore_token
+    *screencast = None;
+    let new_screencast = ScreenCast::start(PERSIST_MODE_PERSISTENT, restore_token)?;
+    let restore_token = new_screencast.restore_token.clone();
+    *screencast = Some(new_screencast);
+
+    Ok(restore_token)
+}
+
+pub fn screencast_restore_token() -> XCapResult<Option<String>> {
+    let screencast = SCREENCAST
+        .lock()
+        .map_err(|_| XCapError::new("Get screencast lock failed"))?;
+
+    Ok(screencast
+        .as_ref()
+        .and_then(|screencast| screencast.restore_token.clone()))
+}
+
+pub fn stop_screencast() -> XCapResult<()> {
+    let mut screencast = SCREENCAST
+        .lock()
+        .map_err(|_| XCapError::new("Get screencast lock failed"))?;
+
+    *screencast = None;
+
+    Ok(())
+}
+
+/// 截取显示器上的区域，`monitor` 是显示器的逻辑坐标，`region` 相对于显示器左上角
+pub fn pipewire_capture(monitor: Rect, region: Rect) -> XCapResult<Frame> {
+    let (stream_info, frames) = {
+        let mut screencast = SCREENCAST
+            .lock()
+            .map_err(|_| XCapError::new("Get screencast lock failed"))?;
+
+        // 流断开（例如用户在系统中停止了共享）后重新建立会话，并尽量恢复之前的授权
+        if !matches!(screencast.as_ref(), Some(screencast) if !screencast.is_broken()) {
+            let (persist_mode, restore_token) = screencast
+                .take()
+                .map(|screencast| (screencast.persist_mode, screencast.restore_token.clone()))
+                .unwrap_or((PERSIST_MODE_TRANSIENT, None));
+
+            *screencast = Some(ScreenCast::start(persist_mode, restore_token.as_deref())?);
+        }
+
+        let screencast = screencast
+            .as_ref()
+            .ok_or_else(|| XCapError::new("Start screencast failed"))?;
+
+        let (stream_info, frames) = screencast
+            .find_stream(&monitor)
+            .ok_or_else(|| XCapError::new("Not found screencast stream for monitor"))?;
+
+        (*stream_info, frames.clone())
+    };
+
+    let frame = frames.latest_frame(Duration::from_secs(5))?;
+
+    let logical_width = stream_info
+        .size
+        .map_or(monitor.width, |(width, _)| width as u32);
+    let scale = frame.width as f32 / logical_width.max(1) as f32;
+
+    let x = (region.x as f32 * scale) as u32;
+    let y = (region.y as f32 * scale) as u32;
+    let width = (region.width as f32 * scale) as u32;
+    let height = (region.height as f32 * scale) as u32;
+
+    frame.crop(x, y, width, height)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::platform::{portal::request_path, test_server::PrivateBus};
+    use dbus::{
+        arg::messageitem::MessageItem,
+        channel::{Channel, MatchingReceiver, Sender},
+        message::MatchRule,
+        Message,
+    };
+    use std::{
+        fs::File,
+        os::fd::IntoRawFd,
+        sync::mpsc::{Receiver, RecvTimeoutError},
+    };
+
+    type PortalCall = (String, Vec<(MessageItem, MessageItem)>);
+
+    /// 模拟门户的 ScreenCast 接口：回复 Request 路径后发出 Response 信号，
+    /// 收到的方法名与 options 通过 channel 返回。每次 Start 返回新的 restore_token
+    fn start_mock_portal(bus: &PrivateBus) -> Receiver<PortalCall> {
+        let address = bus.address().to_string();
+        let (ready_sender, ready_receiver) = mpsc::channel();
+        let (call_sender, call_receiver) = mpsc::channel();
+
+        thread::spawn(move || {
+            let mut channel = Channel::open_private(&address).unwrap();
+            channel.register().unwrap();
+            let conn = Connection::from(channel);
+            conn.request_name("org.freedesktop.portal.Desktop", false, true, false)
+                .unwrap();
+
+            let mut start_count = 0;
+            conn.start_receive(
+                MatchRule::new_method_call(),
+                Box::new(move |message: Message, conn: &Connection| {
+                    let member = message
+                        .member()
+                        .map(|member| member.to_string())
+                        .unwrap_or_default();
+
+                    // options 是最后一个参数
+                    let options = match message.get_items().pop() {
+                        Some(MessageItem::Dict(options)) => options.iter().cloned().collect(),
+                        _ => Vec::new(),
+                    };
+                    let _ = call_sender.send((member.clone(), options));
+
+                    if member == "OpenPipeWireRemote" {
+                        let fd = File::open("/dev/null").unwrap().into_raw_fd();
+                        let reply = message
+                            .method_return()
+                            .append1(unsafe { DbusOwnedFd::new(fd) });
+                        let _ = conn.send(reply);
+                        return true;
+                    }
+
+                    let handle_token = message
+                        .iter_init()
+                        .find_map(|arg| {
+                            let mut options = arg.as_iter()?;
+                            while let (Some(key), Some(value)) = (options.next(), options.next()) {
+                                if key.as_str() == Some("handle_token") {
+                                    return value.as_str().map(String::from);
+                                }
+                            }
+                            None
+                        })
+                        .unwrap();
+                    let sender = message.sender().unwrap().to_string();
+                    let path = request_path(&sender, &handle_token);
+                    let _ = conn.send(message.method_return().append1(path.clone()));
+
+                    let mut results: PropMap = HashMap::new();
+                    match member.as_str() {
+                        "CreateSession" => {
+                            results.insert(
+                                "session_handle".to_string(),
+                                Variant(Box::new(
+                                    "/org/freedesktop/portal/desktop/session/1_0/xcap".to_string(),
+                                )),
+                            );
+                        }
+                        "Start" => {
+                            start_count += 1;
+                            let mut properties: PropMap = HashMap::new();
+                            properties.insert("position".to_string(), Variant(Box::new((1920, 0))));
+                            properties.insert("size".to_string(), Variant(Box::new((2560, 1440))));
+                            results.insert(
+                                "streams".to_string(),
+                                Variant(Box::new(vec![(42u32, properties)])),
+                            );
+                            results.insert(
+                                "restore_token".to_string(),
+                                Variant(Box::new(format!("token-{}", start_count))),
+                            );
+                        }
+                        _ => {}
+                    }
+
+                    let response = Message::new_signal(
+                        path.to_string(),
+                        "org.freedesktop.portal.Request",
+                        "Response",
+                    )
+                    .unwrap()
+                    .append2(0u32, results);
+                    let _ = conn.send(response);
+                    true
+                }),
+            );
+
+            ready_sender.send(()).unwrap();
+            while conn.process(Duration::from_millis(100)).is_ok() {}
+        });
+
+        ready_receiver.recv().unwrap();
+        call_receiver
+    }
+
+    fn next_call(calls: &Receiver<PortalCall>, member: &str) -> Vec<(MessageItem, MessageItem)> {
+        let (call_member, options) = calls.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(call_member, member);
+
+        options
+    }
+
+    fn option<'a>(options: &'a [(MessageItem, MessageItem)], key: &str) -> Option<&'a MessageItem> {
+        options
+            .iter()
+            .find_map(|(option_key, value)| match (option_key, value) {
+                (MessageItem::Str(option_key), MessageItem::Variant(value))
+                    if option_key == key =>
+                {
+                    Some(&**value)
+                }
+                _ => None,
+            })
+    }
+
+    #[test]
+    fn screencast_handshake_with_mock_portal() {
+        let Some(bus) = PrivateBus::start() else {
+            eprintln!("dbus-daemon not found, skipped");
+            return;
+        };
+        let calls = start_mock_portal(&bus);
+        let conn = bus.connect();
+
+        let (session_handle, streams, restore_token) =
+            start_portal_session(&conn, PERSIST_MODE_PERSISTENT, None).unwrap();
+        assert_eq!(
+            session_handle,
+            Path::from("/org/freedesktop/portal/desktop/session/1_0/xcap")
+        );
+        assert_eq!(
+            streams,
+            [StreamInfo {
+                node_id: 42,
+                position: Some((1920, 0)),
+                size: Some((2560, 1440)),
+            }]
+        );
+        assert_eq!(restore_token.as_deref(), Some("token-1"));
+
+        let options = next_call(&calls, "CreateSession");
+        assert!(option(&options, "session_handle_token").is_some());
+
+        let options = next_call(&calls, "SelectSources");
+        assert_eq!(
+            option(&options, "types"),
+            Some(&MessageItem::UInt32(SOURCE_TYPE_MONITOR))
+        );
+        assert_eq!(option(&options, "multiple"), Some(&MessageItem::Bool(true)));
+        assert_eq!(
+            option(&options, "persist_mode"),
+            Some(&MessageItem::UInt32(PERSIST_MODE_PERSISTENT))
+        );
+        assert_eq!(option(&options, "restore_token"), None);
+
+        next_call(&calls, "Start");
+
+        open_pipewire_remote(&conn, &session_handle).unwrap();
+        next_call(&calls, "OpenPipeWireRemote");
+
+        // 上次返回的 restore_token 在下次 SelectSources 时传回门户，门户再返回新的
+        let (_, _, restore_token) =
+            start_portal_session(&conn, PERSIST_MODE_TRANSIENT, restore_token.as_deref()).unwrap();
+        assert_eq!(restore_token.as_deref(), Some("token-2"));
+
+        next_call(&calls, "CreateSession");
+        let options = next_call(&calls, "SelectSources");
+        assert_eq!(
+            option(&options, "persist_mode"),
+            Some(&MessageItem::UInt32(PERSIST_MODE_TRANSIENT))
+        );
+        assert_eq!(
+            option(&options, "restore_token"),
+            Some(&MessageItem::Str("token-1".to_string()))
+        );
+        next_call(&calls, "Start");
+        assert_eq!(
+            calls.recv_timeout(Duration::from_millis(100)),
+            Err(RecvTimeoutError::Timeout)
+        );
+    }
+}
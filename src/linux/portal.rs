@@ -0,0 +1,284 @@
+#[cfg(any(feature = "pipewire", test))]
+use dbus::arg::RefArg;
+use dbus::{
+    arg::{Iter, PropMap, ReadAll, TypeMismatchError},
+    blocking::{Connection, Proxy},
+    message::MatchRule,
+    Message, Path,
+};
+use std::{
+    process,
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use crate::error::{XCapError, XCapResult};
+
+#[derive(Debug)]
+struct PortalResponse {
+    status: u32,
+    results: PropMap,
+}
+
+impl ReadAll for PortalResponse {
+    fn read(i: &mut Iter) -> Result<Self, TypeMismatchError> {
+        Ok(PortalResponse {
+            status: i.read()?,
+            results: i.read()?,
+        })
+    }
+}
+
+pub(super) fn portal_proxy(conn: &Connection) -> Proxy<'_, &Connection> {
+    conn.with_proxy(
+        "org.freedesktop.portal.Desktop",
+        "/org/freedesktop/portal/desktop",
+        Duration::from_secs(10),
+    )
+}
+
+/// 生成进程内唯一的 token，用于 handle_token 与 session_handle_token
+pub(super) fn unique_token() -> String {
+    static COUNTER: AtomicU32 = AtomicU32::new(0);
+
+    format!(
+        "xcap_{}_{}",
+        process::id(),
+        COUNTER.fetch_add(1, Ordering::Relaxed)
+    )
+}
+
+/// Request 对象的路径由连接的 unique name 与 handle_token 决定，
+/// 在调用方法前就能算出来，从而提前订阅 Response 信号
+/// https://flatpak.github.io/xdg-desktop-portal/docs/doc-org.freedesktop.portal.Request.html
+pub(super) fn request_path(unique_name: &str, handle_token: &str) -> Path<'static> {
+    let sender = unique_name.trim_start_matches(':').replace('.', "_");
+
+    Path::from(format!(
+        "/org/freedesktop/portal/desktop/request/{}/{}",
+        sender, handle_token
+    ))
+}
+
+fn wait_response(
+    conn: &Connection,
+    response: &Mutex<Option<Message>>,
+    timeout: Duration,
+) -> XCapResult<PropMap> {
+    let deadline = Instant::now() + timeout;
+
+    loop {
+        let message = response
+            .lock()
+            .map_err(|_| XCapError::new("Get response lock failed"))?
+            .take();
+
+        if let Some(message) = message {
+            let response: PortalResponse = message.read_all()?;
+
+            return match response.status {
+                0 => Ok(response.results),
+                1 => Err(XCapError::UserCancelled),
+                _ => Err(XCapError::new("Portal request failed")),
+            };
+        }
+
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return Err(XCapError::Timeout(String::from(
+                "Waiting for the portal response",
+            )));
+        }
+
+        conn.process(remaining.min(Duration::from_millis(100)))?;
+    }
+}
+
+/// 调用返回 Request 对象的门户方法，并等待它的 Response 信号。
+/// `call` 收到本次请求的 handle_token，需要把它放进方法的 options 里
+pub(super) fn portal_request<F>(
+    conn: &Connection,
+    timeout: Duration,
+    call: F,
+) -> XCapResult<PropMap>
+where
+    F: FnOnce(&str) -> XCapResult<()>,
+{
+    let handle_token = unique_token();
+    let response: Arc<Mutex<Option<Message>>> = Arc::new(Mutex::new(None));
+    let response_res = response.clone();
+
+    let match_rule = MatchRule::new_signal("org.freedesktop.portal.Request", "Response")
+        .with_path(request_path(&conn.unique_name(), &handle_token));
+    let match_token = conn.add_match(match_rule, move |_: (), _conn, message| {
+        if let (Ok(mut response), Ok(message)) = (response.lock(), message.duplicate()) {
+            *response = Some(message);
+        }
+
+        true
+    })?;
+
+    let result = call(&handle_token).and_then(|_| wait_response(conn, &response_res, timeout));
+
+    if let Err(err) = conn.remove_match(match_token) {
+        log::debug!("Remove portal response match failed: {}", err);
+    }
+
+    result
+}
+
+/// ScreenCast Start 返回的一路视频流，位置与大小是合成器的逻辑坐标
+#[cfg(any(feature = "pipewire", test))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) struct StreamInfo {
+    pub node_id: u32,
+    pub position: Option<(i32, i32)>,
+    pub size: Option<(i32, i32)>,
+}
+
+/// 从 a{sv} 中的 (ii) 结构体读取两个整数，会先解开外层的 variant
+#[cfg(any(feature = "pipewire", test))]
+fn read_pair(arg: &dyn RefArg) -> Option<(i32, i32)> {
+    let mut iter = arg.as_iter()?;
+    let first = iter.next()?;
+
+    match iter.next() {
+        Some(second) => Some((first.as_i64()? as i32, second.as_i64()? as i32)),
+        None => read_pair(first),
+    }
+}
+
+/// 读取 ScreenCast Start 结果中的 streams
+#[cfg(any(feature = "pipewire", test))]
+pub(super) fn parse_streams(results: &PropMap) -> Vec<StreamInfo> {
+    let Some(streams) = results
+        .get("streams")
+        .and_then(|streams| streams.0.as_iter())
+    else {
+        return Vec::new();
+    };
+
+    // streams 的类型是 a(ua{sv})
+    streams
+        .filter_map(|stream| {
+            let mut fields = stream.as_iter()?;
+            let node_id = fields.next()?.as_u64()? as u32;
+            let mut properties = fields.next()?.as_iter()?;
+
+            let mut stream_info = StreamInfo {
+                node_id,
+                position: None,
+                size: None,
+            };
+
+            while let (Some(key), Some(value)) = (properties.next(), properties.next()) {
+                match key.as_str() {
+                    Some("position") => stream_info.position = read_pair(value),
+                    Some("size") => stream_info.size = read_pair(value),
+                    _ => {}
+                }
+            }
+
+            Some(stream_info)
+        })
+        .collect()
+}
+
+/// restore_token 只能使用一次，每次 Start 都会返回新的
+#[cfg(any(feature = "pipewire", test))]
+pub(super) fn parse_restore_token(results: &PropMap) -> Option<String> {
+    results
+        .get("restore_token")
+        .and_then(|restore_token| restore_token.as_str())
+        .map(String::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dbus::arg::Variant;
+    use std::collections::HashMap;
+
+    /// 经过一次消息的序列化，得到与门户回复相同的动态类型
+    fn round_trip(results: PropMap) -> PropMap {
+        Message::new_signal("/", "org.freedesktop.portal.Request", "Response")
+            .unwrap()
+            .append1(results)
+            .read1()
+            .unwrap()
+    }
+
+    fn stream(node_id: u32, properties: &[(&str, (i32, i32))]) -> (u32, PropMap) {
+        let properties = properties
+            .iter()
+            .map(|&(key, pair)| {
+                let value: Box<dyn RefArg> = Box::new(pair);
+                (key.to_string(), Variant(value))
+            })
+            .collect();
+
+        (node_id, properties)
+    }
+
+    #[test]
+    fn request_path_from_unique_name() {
+        assert_eq!(
+            request_path(":1.42", "xcap_7_0"),
+            Path::from("/org/freedesktop/portal/desktop/request/1_42/xcap_7_0")
+        );
+        assert_eq!(
+            request_path(":1.2.3", "token"),
+            Path::from("/org/freedesktop/portal/desktop/request/1_2_3/token")
+        );
+    }
+
+    #[test]
+    fn streams_and_restore_token() {
+        let streams = vec![
+            stream(42, &[("position", (1920, 0)), ("size", (2560, 1440))]),
+            stream(43, &[("size", (1920, 1080))]),
+            stream(44, &[]),
+        ];
+
+        let mut results: PropMap = HashMap::new();
+        results.insert("streams".to_string(), Variant(Box::new(streams)));
+        results.insert(
+            "restore_token".to_string(),
+            Variant(Box::new("token".to_string())),
+        );
+        let results = round_trip(results);
+
+        assert_eq!(
+            parse_streams(&results),
+            [
+                StreamInfo {
+                    node_id: 42,
+                    position: Some((1920, 0)),
+                    size: Some((2560, 1440)),
+                },
+                StreamInfo {
+                    node_id: 43,
+                    position: None,
+                    size: Some((1920, 1080)),
+                },
+                StreamInfo {
+                    node_id: 44,
+                    position: None,
+                    size: None,
+                },
+            ]
+        );
+        assert_eq!(parse_restore_token(&results).as_deref(), Some("token"));
+    }
+
+    #[test]
+    fn missing_streams_and_restore_token() {
+        let results = round_trip(HashMap::new());
+
+        assert!(parse_streams(&results).is_empty());
+        assert_eq!(parse_restore_token(&results), None);
+    }
+}
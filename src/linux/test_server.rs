@@ -0,0 +1,85 @@
+use dbus::{blocking::Connection, channel::Channel};
+use std::{
+    io::{self, BufRead, BufReader},
+    process::{Child, Command, Stdio},
+};
+
+/// 测试用的服务进程，准备好后在 stdout 输出一行地址，Drop 时结束进程
+pub(super) struct TestServer {
+    child: Child,
+    pub address: String,
+}
+
+impl TestServer {
+    pub fn start(command: &mut Command) -> io::Result<TestServer> {
+        let mut child = command
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()?;
+
+        // 先构造 TestServer，读取失败时也会结束进程
+        let stdout = child.stdout.take();
+        let mut test_server = TestServer {
+            child,
+            address: String::new(),
+        };
+
+        let stdout = stdout.ok_or_else(|| io::Error::other("No stdout"))?;
+        BufReader::new(stdout).read_line(&mut test_server.address)?;
+        test_server.address = test_server.address.trim().to_string();
+
+        if test_server.address.is_empty() {
+            return Err(io::Error::other(
+                "Server exited before printing its address",
+            ));
+        }
+
+        Ok(test_server)
+    }
+}
+
+/// 启动只有一个 64x48 屏幕、背景为黑色的 Xvfb，返回的 address 是 display 编号
+pub(super) fn start_xvfb(depth: u8) -> TestServer {
+    TestServer::start(
+        Command::new("Xvfb")
+            .args(["-displayfd", "1", "-nolisten", "tcp", "-br", "-screen", "0"])
+            .arg(format!("64x48x{}", depth)),
+    )
+    .expect("Start Xvfb failed")
+}
+
+impl Drop for TestServer {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+/// 测试用的私有 session bus，Drop 时结束 dbus-daemon
+pub(super) struct PrivateBus {
+    daemon: TestServer,
+}
+
+impl PrivateBus {
+    /// 没有安装 dbus-daemon 时返回 None，调用方跳过测试
+    pub fn start() -> Option<PrivateBus> {
+        let daemon = TestServer::start(Command::new("dbus-daemon").args([
+            "--session",
+            "--nofork",
+            "--print-address=1",
+        ]))
+        .ok()?;
+
+        Some(PrivateBus { daemon })
+    }
+
+    pub fn address(&self) -> &str {
+        &self.daemon.address
+    }
+
+    pub fn connect(&self) -> Connection {
+        let mut channel = Channel::open_private(self.address()).unwrap();
+        channel.register().unwrap();
+        Connection::from(channel)
+    }
+}
@@ -1,42 +1,74 @@
+use encoding_rs::{Encoding, EUC_JP, EUC_KR, GBK};
 use image::{open, RgbaImage};
+use std::{
+    env::{temp_dir, var_os},
+    fs::{self, DirBuilder},
+    os::unix::fs::DirBuilderExt,
+    path::{Path, PathBuf},
+};
 
 use crate::error::XCapResult;
 
-pub(super) struct Rect {
-    x: i32,
-    y: i32,
-    width: u32,
-    height: u32,
+use super::portal::unique_token;
+
+/// 只有当前用户可以访问的临时目录，Drop 时连同其中的截图一起删除
+pub(super) struct PrivateTempDir {
+    path: PathBuf,
+}
+
+impl PrivateTempDir {
+    pub fn new() -> XCapResult<PrivateTempDir> {
+        // XDG_RUNTIME_DIR 本身就是 0700 的 tmpfs，截图不会写入磁盘
+        let parent = var_os("XDG_RUNTIME_DIR")
+            .map(PathBuf::from)
+            .filter(|path| path.is_dir())
+            .unwrap_or_else(temp_dir);
+        let path = parent.join(unique_token());
+
+        // 目录已存在时创建失败，避免使用其他用户预先创建的目录或符号链接
+        DirBuilder::new().mode(0o700).create(&path)?;
+
+        Ok(PrivateTempDir { path })
+    }
+
+    pub fn join(&self, filename: &str) -> PathBuf {
+        self.path.join(filename)
+    }
 }
 
-impl Rect {
-    // 计算两个矩形的交集面积
-    pub(super) fn new(x: i32, y: i32, width: u32, height: u32) -> Rect {
-        Rect {
-            x,
-            y,
-            width,
-            height,
+impl Drop for PrivateTempDir {
+    fn drop(&mut self) {
+        if let Err(err) = fs::remove_dir_all(&self.path) {
+            log::error!("Remove {:?} failed: {}", self.path, err);
         }
     }
+}
 
-    // 计算两个矩形的交集面积
-    pub(super) fn overlap_area(&self, other_rect: Rect) -> i32 {
-        let left = self.x.max(other_rect.x);
-        let top = self.y.max(other_rect.y);
-        let right = (self.x + self.width as i32).min(other_rect.x + other_rect.width as i32);
-        let bottom = (self.y + self.height as i32).min(other_rect.y + other_rect.height as i32);
+/// 由其它进程写入的截图文件，Drop 时删除
+pub(super) struct TempFile {
+    path: PathBuf,
+}
+
+impl TempFile {
+    pub fn new(path: PathBuf) -> TempFile {
+        TempFile { path }
+    }
 
-        // 与0比较，如果小于0则表示两个矩形无交集
-        let width = (right - left).max(0);
-        let height = (bottom - top).max(0);
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
 
-        width * height
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        if let Err(err) = fs::remove_file(&self.path) {
+            log::error!("Remove {:?} failed: {}", self.path, err);
+        }
     }
 }
 
 pub(super) fn png_to_rgba_image(
-    filename: &String,
+    filename: &Path,
     x: i32,
     y: i32,
     width: i32,
@@ -46,3 +78,206 @@ pub(super) fn png_to_rgba_image(
     dynamic_image = dynamic_image.crop(x as u32, y as u32, width as u32, height as u32);
     Ok(dynamic_image.to_rgba8())
 }
+
+// STRING 类型的属性使用 ISO-8859-1 编码，每个字节就是一个 Unicode 码位
+pub(super) fn decode_latin1(bytes: &[u8]) -> String {
+    bytes.iter().map(|&byte| byte as char).collect()
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum CompoundTextCharset {
+    Latin1,
+    Gb2312,
+    JisX0208,
+    Ksc5601,
+    Unsupported,
+}
+
+impl CompoundTextCharset {
+    // 94^2 字符集的最终字节，A: GB2312，B: JIS X 0208，C: KSC 5601
+    fn from_multi_byte_final(final_byte: u8) -> CompoundTextCharset {
+        match final_byte {
+            b'A' => CompoundTextCharset::Gb2312,
+            b'B' => CompoundTextCharset::JisX0208,
+            b'C' => CompoundTextCharset::Ksc5601,
+            _ => CompoundTextCharset::Unsupported,
+        }
+    }
+
+    // 多字节字符集的字节设置最高位后就是对应的 EUC 编码
+    fn euc_encoding(self) -> Option<&'static Encoding> {
+        match self {
+            CompoundTextCharset::Gb2312 => Some(GBK),
+            CompoundTextCharset::JisX0208 => Some(EUC_JP),
+            CompoundTextCharset::Ksc5601 => Some(EUC_KR),
+            CompoundTextCharset::Latin1 | CompoundTextCharset::Unsupported => None,
+        }
+    }
+}
+
+// COMPOUND_TEXT 解码
+// https://www.x.org/releases/X11R7.6/doc/xorg-docs/specs/CTEXT/ctext.html
+// 支持 ISO-8859-1、GB2312、JIS X 0208、KSC 5601 与 XFree86 的 UTF-8 扩展段（ESC % G ... ESC % @），
+// 其它字符集以 U+FFFD 代替
+pub(super) fn decode_compound_text(bytes: &[u8]) -> String {
+    const ESC: u8 = 0x1b;
+    const CSI: u8 = 0x9b;
+
+    let mut text = String::new();
+    let mut gl = CompoundTextCharset::Latin1;
+    let mut gr = CompoundTextCharset::Latin1;
+    let mut index = 0;
+
+    while index < bytes.len() {
+        let byte = bytes[index];
+
+        if byte == ESC {
+            let intermediate = &bytes[index + 1..];
+            match intermediate {
+                // UTF-8 段，直到 ESC % @ 结束
+                [b'%', b'G', rest @ ..] => {
+                    let end = rest
+                        .windows(3)
+                        .position(|window| window == [ESC, b'%', b'@'])
+                        .unwrap_or(rest.len());
+                    text.push_str(&String::from_utf8_lossy(&rest[..end]));
+                    index += 3 + end + 3;
+                }
+                [b'(', final_byte, ..] => {
+                    gl = if *final_byte == b'B' {
+                        CompoundTextCharset::Latin1
+                    } else {
+                        CompoundTextCharset::Unsupported
+                    };
+                    index += 3;
+                }
+                [b'-', final_byte, ..] => {
+                    gr = if *final_byte == b'A' {
+                        CompoundTextCharset::Latin1
+                    } else {
+                        CompoundTextCharset::Unsupported
+                    };
+                    index += 3;
+                }
+                // 多字节字符集
+                [b'$', b'(', final_byte, ..] => {
+                    gl = CompoundTextCharset::from_multi_byte_final(*final_byte);
+                    index += 4;
+                }
+                [b'$', b')', final_byte, ..] => {
+                    gr = CompoundTextCharset::from_multi_byte_final(*final_byte);
+                    index += 4;
+                }
+                // 带长度的扩展段：ESC % / F M L，M L 为长度
+                [b'%', b'/', _, m, l, ..] => {
+                    let length =
+                        m.saturating_sub(128) as usize * 128 + l.saturating_sub(128) as usize;
+                    text.push(char::REPLACEMENT_CHARACTER);
+                    index += 6 + length;
+                }
+                // 其它转义序列：中间字节 0x20-0x2f，以 0x30-0x7e 结束，末尾不完整的序列直接丢弃
+                _ => {
+                    index += 1;
+                    while index < bytes.len() && (0x20..=0x2f).contains(&bytes[index]) {
+                        index += 1;
+                    }
+                    index += 1;
+                }
+            }
+            continue;
+        }
+
+        // 文字方向控制序列，CSI ... 以 0x40-0x7e 结束
+        if byte == CSI {
+            index += 1;
+            while index < bytes.len() && !(0x40..=0x7e).contains(&bytes[index]) {
+                index += 1;
+            }
+            index += 1;
+            continue;
+        }
+
+        let is_gl = byte < 0x80;
+        let charset = if is_gl { gl } else { gr };
+
+        if let Some(encoding) = charset.euc_encoding() {
+            // 同一侧连续的图形字符一起解码，落单的字节由 encoding_rs 替换为 U+FFFD
+            let length = bytes[index..]
+                .iter()
+                .take_while(|&&byte| {
+                    (byte < 0x80) == is_gl && (0x21..=0x7e).contains(&(byte & 0x7f))
+                })
+                .count();
+
+            if length > 0 {
+                let euc_bytes: Vec<u8> = bytes[index..index + length]
+                    .iter()
+                    .map(|byte| byte | 0x80)
+                    .collect();
+                text.push_str(&encoding.decode_without_bom_handling(&euc_bytes).0);
+                index += length;
+                continue;
+            }
+        }
+
+        if byte == b'\n' || byte == b'\t' || byte == b' ' || charset == CompoundTextCharset::Latin1
+        {
+            text.push(byte as char);
+        } else if !text.ends_with(char::REPLACEMENT_CHARACTER) {
+            text.push(char::REPLACEMENT_CHARACTER);
+        }
+        index += 1;
+    }
+
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn latin1() {
+        assert_eq!(decode_latin1(b"caf\xe9 \xa9"), "café ©");
+        assert_eq!(decode_compound_text(b"caf\xe9 \xa9"), "café ©");
+        // ESC ( B 与 ESC - A 是默认字符集
+        assert_eq!(decode_compound_text(b"\x1b(Bab\x1b-A\xe9"), "abé");
+    }
+
+    #[test]
+    fn utf8_segment() {
+        let bytes = [b"a\x1b%G".as_slice(), "中文 ✓".as_bytes(), b"\x1b%@b"].concat();
+        assert_eq!(decode_compound_text(&bytes), "a中文 ✓b");
+
+        // 没有结束序列时解码到末尾
+        let bytes = [b"\x1b%G".as_slice(), "中文".as_bytes()].concat();
+        assert_eq!(decode_compound_text(&bytes), "中文");
+    }
+
+    #[test]
+    fn multi_byte_segments() {
+        // GB2312 放在 GR，字节与 EUC-CN 相同
+        assert_eq!(
+            decode_compound_text(b"a\x1b$)A\xd6\xd0\xce\xc4 b"),
+            "a中文 b"
+        );
+        // GB2312 放在 GL，字节没有设置最高位
+        assert_eq!(decode_compound_text(b"\x1b$(AVPND\x1b(Bb"), "中文b");
+        // JIS X 0208：日本
+        assert_eq!(decode_compound_text(b"\x1b$)B\xc6\xfc\xcb\xdc"), "日本");
+        // KSC 5601：한국
+        assert_eq!(decode_compound_text(b"\x1b$)C\xc7\xd1\xb1\xb9"), "한국");
+        // 不认识的字符集
+        assert_eq!(decode_compound_text(b"\x1b$)G\xc7\xd1a"), "\u{fffd}a");
+    }
+
+    #[test]
+    fn truncated_escape() {
+        assert_eq!(decode_compound_text(b"ab\x1b"), "ab");
+        assert_eq!(decode_compound_text(b"ab\x1b$"), "ab");
+        assert_eq!(decode_compound_text(b"ab\x1b$)"), "ab");
+        assert_eq!(decode_compound_text(b"ab\x1b%/1"), "ab");
+        // 最后一个双字节字符不完整
+        assert_eq!(decode_compound_text(b"\x1b$)A\xd6\xd0\xce"), "中\u{fffd}");
+    }
+}
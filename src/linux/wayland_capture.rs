@@ -1,48 +1,18 @@
 use dbus::{
-    arg::{AppendAll, Iter, IterAppend, PropMap, ReadAll, RefArg, TypeMismatchError, Variant},
+    arg::{PropMap, RefArg, Variant},
     blocking::Connection,
-    message::{MatchRule, SignalArgs},
+    Path,
 };
 use image::RgbaImage;
 use percent_encoding::percent_decode;
-use std::{
-    collections::HashMap,
-    env::temp_dir,
-    fs::{self},
-    sync::{Arc, Mutex},
-    time::{Duration, SystemTime, UNIX_EPOCH},
-};
+use std::{collections::HashMap, path::PathBuf, time::Duration};
 
 use crate::error::{XCapError, XCapResult};
 
-use super::{impl_monitor::ImplMonitor, utils::png_to_rgba_image};
-
-#[derive(Debug)]
-struct OrgFreedesktopPortalRequestResponse {
-    status: u32,
-    results: PropMap,
-}
-
-impl AppendAll for OrgFreedesktopPortalRequestResponse {
-    fn append(&self, i: &mut IterAppend) {
-        RefArg::append(&self.status, i);
-        RefArg::append(&self.results, i);
-    }
-}
-
-impl ReadAll for OrgFreedesktopPortalRequestResponse {
-    fn read(i: &mut Iter) -> Result<Self, TypeMismatchError> {
-        Ok(OrgFreedesktopPortalRequestResponse {
-            status: i.read()?,
-            results: i.read()?,
-        })
-    }
-}
-
-impl SignalArgs for OrgFreedesktopPortalRequestResponse {
-    const NAME: &'static str = "Response";
-    const INTERFACE: &'static str = "org.freedesktop.portal.Request";
-}
+use super::{
+    portal::{portal_proxy, portal_request},
+    utils::{png_to_rgba_image, PrivateTempDir, TempFile},
+};
 
 fn org_gnome_shell_screenshot(
     conn: &Connection,
@@ -57,28 +27,17 @@ fn org_gnome_shell_screenshot(
         Duration::from_secs(10),
     );
 
-    let timestamp = SystemTime::now().duration_since(UNIX_EPOCH)?;
-
-    let dirname = temp_dir().join("screenshot");
-
-    fs::create_dir_all(&dirname)?;
+    // org.gnome.Shell.Screenshot 只能写入文件，写到私有目录中，返回时删除
+    let dir = PrivateTempDir::new()?;
+    let path = dir.join("screenshot.png");
 
-    let mut path = dirname.join(timestamp.as_micros().to_string());
-    path.set_extension("png");
-
-    let filename = path.to_string_lossy().to_string();
-
-    proxy.method_call(
+    proxy.method_call::<(), _, _, _>(
         "org.gnome.Shell.Screenshot",
         "ScreenshotArea",
-        (x, y, width, height, false, &filename),
+        (x, y, width, height, false, path.to_string_lossy().as_ref()),
     )?;
 
-    let rgba_image = png_to_rgba_image(&filename, 0, 0, width, height)?;
-
-    fs::remove_file(&filename)?;
-
-    Ok(rgba_image)
+    png_to_rgba_image(&path, 0, 0, width, height)
 }
 
 fn org_freedesktop_portal_screenshot(
@@ -88,93 +47,48 @@ fn org_freedesktop_portal_screenshot(
     width: i32,
     height: i32,
 ) -> XCapResult<RgbaImage> {
-    let status: Arc<Mutex<Option<u32>>> = Arc::new(Mutex::new(None));
-    let status_res = status.clone();
-    let path: Arc<Mutex<String>> = Arc::new(Mutex::new(String::new()));
-    let path_res = path.clone();
-
-    let match_rule = MatchRule::new_signal("org.freedesktop.portal.Request", "Response");
-    conn.add_match(
-        match_rule,
-        move |response: OrgFreedesktopPortalRequestResponse, _conn, _msg| {
-            if let Ok(mut status) = status.lock() {
-                *status = Some(response.status);
-            }
-
-            let uri = response.results.get("uri").and_then(|str| str.as_str());
-            if let (Some(uri_str), Ok(mut path)) = (uri, path.lock()) {
-                *path = uri_str[7..].to_string();
-            }
-
-            true
-        },
-    )?;
-
-    let proxy = conn.with_proxy(
-        "org.freedesktop.portal.Desktop",
-        "/org/freedesktop/portal/desktop",
-        Duration::from_millis(10000),
-    );
-
-    let mut options: PropMap = HashMap::new();
-    options.insert(
-        String::from("handle_token"),
-        Variant(Box::new(String::from("1234"))),
-    );
-    options.insert(String::from("modal"), Variant(Box::new(true)));
-    options.insert(String::from("interactive"), Variant(Box::new(false)));
-
-    proxy.method_call(
-        "org.freedesktop.portal.Screenshot",
-        "Screenshot",
-        ("", options),
-    )?;
-
-    // wait 60 seconds for user interaction
-    for _ in 0..60 {
-        let result = conn.process(Duration::from_millis(1000))?;
-        let status = status_res
-            .lock()
-            .map_err(|_| XCapError::new("Get status lock failed"))?;
-
-        if result && status.is_some() {
-            break;
-        }
-    }
-
-    let status = status_res
-        .lock()
-        .map_err(|_| XCapError::new("Get status lock failed"))?;
-    let status = *status;
-
-    let path = path_res
-        .lock()
-        .map_err(|_| XCapError::new("Get path lock failed"))?;
-    let path = &*path;
-
-    if status.ne(&Some(0)) || path.is_empty() {
-        if !path.is_empty() {
-            fs::remove_file(path)?;
-        }
-        return Err(XCapError::new("Screenshot failed or canceled"));
-    }
-
+    let proxy = portal_proxy(conn);
+
+    // 等待 60 秒，门户可能需要用户授权
+    let results = portal_request(conn, Duration::from_secs(60), |handle_token| {
+        let mut options: PropMap = HashMap::new();
+        options.insert(
+            String::from("handle_token"),
+            Variant(Box::new(handle_token.to_string())),
+        );
+        options.insert(String::from("modal"), Variant(Box::new(true)));
+        options.insert(String::from("interactive"), Variant(Box::new(false)));
+
+        proxy.method_call::<(Path<'static>,), _, _, _>(
+            "org.freedesktop.portal.Screenshot",
+            "Screenshot",
+            ("", options),
+        )?;
+
+        Ok(())
+    })?;
+
+    let path = results
+        .get("uri")
+        .and_then(|uri| uri.as_str())
+        .and_then(|uri| uri.strip_prefix("file://"))
+        .ok_or_else(|| XCapError::new("Screenshot failed"))?;
+
+    // 门户把截图保存在自己选择的位置，读取后无论成功与否都删除
     let filename = percent_decode(path.as_bytes()).decode_utf8()?.to_string();
-    let rgba_image = png_to_rgba_image(&filename, x, y, width, height)?;
+    let temp_file = TempFile::new(PathBuf::from(filename));
 
-    fs::remove_file(&filename)?;
-
-    Ok(rgba_image)
+    png_to_rgba_image(temp_file.path(), x, y, width, height)
 }
 
-pub fn wayland_capture(impl_monitor: &ImplMonitor) -> XCapResult<RgbaImage> {
-    let x = ((impl_monitor.x as f32) * impl_monitor.scale_factor) as i32;
-    let y = ((impl_monitor.y as f32) * impl_monitor.scale_factor) as i32;
-    let width = ((impl_monitor.width as f32) * impl_monitor.scale_factor) as i32;
-    let height = ((impl_monitor.height as f32) * impl_monitor.scale_factor) as i32;
-
+pub fn gnome_shell_capture(x: i32, y: i32, width: i32, height: i32) -> XCapResult<RgbaImage> {
     let conn = Connection::new_session()?;
 
     org_gnome_shell_screenshot(&conn, x, y, width, height)
-        .or_else(|_| org_freedesktop_portal_screenshot(&conn, x, y, width, height))
+}
+
+pub fn portal_capture(x: i32, y: i32, width: i32, height: i32) -> XCapResult<RgbaImage> {
+    let conn = Connection::new_session()?;
+
+    org_freedesktop_portal_screenshot(&conn, x, y, width, height)
 }
@@ -0,0 +1,320 @@
+use wayland_client::{
+    delegate_dispatch,
+    globals::{registry_queue_init, GlobalList, GlobalListContents},
+    protocol::{
+        wl_output::{self, Transform, WlOutput},
+        wl_registry::{self, WlRegistry},
+    },
+    Connection, Dispatch, EventQueue, QueueHandle, WEnum,
+};
+use wayland_protocols::xdg::xdg_output::zv1::client::{
+    zxdg_output_manager_v1::ZxdgOutputManagerV1,
+    zxdg_output_v1::{self, ZxdgOutputV1},
+};
+
+use crate::{error::XCapResult, rect::Rect};
+
+/// wl_output 与 xdg-output 描述的一个显示器，坐标是合成器的逻辑坐标
+#[derive(Debug, Clone)]
+pub(super) struct WlOutputInfo {
+    pub output: WlOutput,
+    /// wl_output 全局对象的 name，在输出断开前保持不变
+    pub id: u32,
+    pub name: String,
+    pub x: i32,
+    pub y: i32,
+    pub width: i32,
+    pub height: i32,
+    pub transform: Transform,
+    /// 刷新率，单位是 mHz
+    pub refresh: i32,
+    scale: i32,
+    mode_width: i32,
+    mode_height: i32,
+    has_logical_size: bool,
+}
+
+impl WlOutputInfo {
+    fn new(output: WlOutput, id: u32) -> WlOutputInfo {
+        WlOutputInfo {
+            output,
+            id,
+            name: String::new(),
+            x: 0,
+            y: 0,
+            width: 0,
+            height: 0,
+            transform: Transform::Normal,
+            refresh: 0,
+            scale: 1,
+            mode_width: 0,
+            mode_height: 0,
+            has_logical_size: false,
+        }
+    }
+
+    /// 没有 xdg-output 时，用分辨率、缩放与旋转推算逻辑大小
+    fn update_logical_size(&mut self) {
+        if self.has_logical_size {
+            return;
+        }
+
+        let scale = self.scale.max(1);
+        let (width, height) = match self.transform {
+            Transform::_90 | Transform::_270 | Transform::Flipped90 | Transform::Flipped270 => {
+                (self.mode_height, self.mode_width)
+            }
+            _ => (self.mode_width, self.mode_height),
+        };
+
+        self.width = width / scale;
+        self.height = height / scale;
+    }
+
+    /// 物理像素与逻辑坐标的比例，支持小数缩放
+    pub fn scale_factor(&self) -> f32 {
+        let mode_width = match self.transform {
+            Transform::_90 | Transform::_270 | Transform::Flipped90 | Transform::Flipped270 => {
+                self.mode_height
+            }
+            _ => self.mode_width,
+        };
+
+        if self.width > 0 && mode_width > 0 {
+            mode_width as f32 / self.width as f32
+        } else {
+            self.scale as f32
+        }
+    }
+
+    /// 逆时针旋转的角度
+    pub fn rotation(&self) -> f32 {
+        match self.transform {
+            Transform::_90 | Transform::Flipped90 => 90.0,
+            Transform::_180 | Transform::Flipped180 => 180.0,
+            Transform::_270 | Transform::Flipped270 => 270.0,
+            _ => 0.0,
+        }
+    }
+}
+
+pub(super) trait WlOutputsHandler {
+    fn outputs(&mut self) -> &mut Vec<WlOutputInfo>;
+}
+
+/// 通过 `delegate_dispatch!` 处理 wl_output 与 xdg-output 事件，user data 是输出的下标
+pub(super) struct WlOutputs;
+
+impl<D> Dispatch<WlOutput, usize, D> for WlOutputs
+where
+    D: WlOutputsHandler + Dispatch<WlOutput, usize>,
+{
+    fn event(
+        state: &mut D,
+        _output: &WlOutput,
+        event: wl_output::Event,
+        index: &usize,
+        _conn: &Connection,
+        _qh: &QueueHandle<D>,
+    ) {
+        let Some(output_info) = state.outputs().get_mut(*index) else {
+            return;
+        };
+
+        match event {
+            wl_output::Event::Geometry {
+                x, y, transform, ..
+            } => {
+                // xdg-output 的逻辑坐标更准确，只在没有时使用
+                if !output_info.has_logical_size {
+                    output_info.x = x;
+                    output_info.y = y;
+                }
+                if let WEnum::Value(transform) = transform {
+                    output_info.transform = transform;
+                }
+            }
+            wl_output::Event::Mode {
+                flags,
+                width,
+                height,
+                refresh,
+            } => {
+                if matches!(flags, WEnum::Value(flags) if flags.contains(wl_output::Mode::Current))
+                {
+                    output_info.mode_width = width;
+                    output_info.mode_height = height;
+                    output_info.refresh = refresh;
+                }
+            }
+            wl_output::Event::Scale { factor } => output_info.scale = factor,
+            wl_output::Event::Name { name } => output_info.name = name,
+            _ => {}
+        }
+    }
+}
+
+impl<D> Dispatch<ZxdgOutputV1, usize, D> for WlOutputs
+where
+    D: WlOutputsHandler + Dispatch<ZxdgOutputV1, usize>,
+{
+    fn event(
+        state: &mut D,
+        _xdg_output: &ZxdgOutputV1,
+        event: zxdg_output_v1::Event,
+        index: &usize,
+        _conn: &Connection,
+        _qh: &QueueHandle<D>,
+    ) {
+        let Some(output_info) = state.outputs().get_mut(*index) else {
+            return;
+        };
+
+        match event {
+            zxdg_output_v1::Event::LogicalPosition { x, y } => {
+                output_info.x = x;
+                output_info.y = y;
+            }
+            zxdg_output_v1::Event::LogicalSize { width, height } => {
+                output_info.width = width;
+                output_info.height = height;
+                output_info.has_logical_size = true;
+            }
+            // wl_output 版本 4 之前没有 name 事件
+            zxdg_output_v1::Event::Name { name } if output_info.name.is_empty() => {
+                output_info.name = name;
+            }
+            _ => {}
+        }
+    }
+}
+
+impl<D> Dispatch<ZxdgOutputManagerV1, (), D> for WlOutputs
+where
+    D: Dispatch<ZxdgOutputManagerV1, ()>,
+{
+    fn event(
+        _state: &mut D,
+        _manager: &ZxdgOutputManagerV1,
+        _event: <ZxdgOutputManagerV1 as wayland_client::Proxy>::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qh: &QueueHandle<D>,
+    ) {
+    }
+}
+
+/// 绑定所有 wl_output，并通过 xdg-output 获取逻辑坐标与名称
+pub(super) fn bind_outputs<D>(
+    globals: &GlobalList,
+    event_queue: &mut EventQueue<D>,
+    state: &mut D,
+) -> XCapResult<()>
+where
+    D: WlOutputsHandler
+        + Dispatch<WlOutput, usize>
+        + Dispatch<ZxdgOutputV1, usize>
+        + Dispatch<ZxdgOutputManagerV1, ()>
+        + 'static,
+{
+    let qh = event_queue.handle();
+
+    for global in globals.contents().clone_list() {
+        if global.interface != "wl_output" {
+            continue;
+        }
+
+        let index = state.outputs().len();
+        let output: WlOutput =
+            globals
+                .registry()
+                .bind(global.name, global.version.min(4), &qh, index);
+        state.outputs().push(WlOutputInfo::new(output, global.name));
+    }
+
+    if let Ok(xdg_output_manager) = globals.bind::<ZxdgOutputManagerV1, _, _>(&qh, 1..=3, ()) {
+        for (index, output_info) in state.outputs().iter().enumerate() {
+            xdg_output_manager.get_xdg_output(&output_info.output, &qh, index);
+        }
+    }
+
+    // 绑定后合成器会立即发送 wl_output 与 xdg-output 的全部事件
+    event_queue.roundtrip(state)?;
+
+    for output_info in state.outputs().iter_mut() {
+        output_info.update_logical_size();
+    }
+
+    Ok(())
+}
+
+/// 找到 `Monitor` 对应的输出：XWayland 使用与 wl_output 相同的名称，其次按逻辑位置匹配
+pub(super) fn find_output<'a>(
+    outputs: &'a [WlOutputInfo],
+    name: &str,
+    monitor: &Rect,
+) -> Option<&'a WlOutputInfo> {
+    if let [output_info] = outputs {
+        return Some(output_info);
+    }
+
+    outputs
+        .iter()
+        .find(|output_info| !name.is_empty() && output_info.name == name)
+        .or_else(|| {
+            outputs
+                .iter()
+                .find(|output_info| (output_info.x, output_info.y) == (monitor.x, monitor.y))
+        })
+}
+
+/// 把相对于 `Monitor` 的区域换算为相对于输出的逻辑坐标，两者的缩放可能不同
+pub(super) fn to_output_region(output_info: &WlOutputInfo, monitor: &Rect, region: &Rect) -> Rect {
+    let scale_x = output_info.width as f32 / monitor.width.max(1) as f32;
+    let scale_y = output_info.height as f32 / monitor.height.max(1) as f32;
+
+    Rect::new(
+        (region.x as f32 * scale_x) as i32,
+        (region.y as f32 * scale_y) as i32,
+        (region.width as f32 * scale_x) as u32,
+        (region.height as f32 * scale_y) as u32,
+    )
+}
+
+#[derive(Debug, Default)]
+struct OutputsState {
+    outputs: Vec<WlOutputInfo>,
+}
+
+impl WlOutputsHandler for OutputsState {
+    fn outputs(&mut self) -> &mut Vec<WlOutputInfo> {
+        &mut self.outputs
+    }
+}
+
+impl Dispatch<WlRegistry, GlobalListContents> for OutputsState {
+    fn event(
+        _state: &mut Self,
+        _registry: &WlRegistry,
+        _event: wl_registry::Event,
+        _data: &GlobalListContents,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+    }
+}
+
+delegate_dispatch!(OutputsState: [WlOutput: usize] => WlOutputs);
+delegate_dispatch!(OutputsState: [ZxdgOutputV1: usize] => WlOutputs);
+delegate_dispatch!(OutputsState: [ZxdgOutputManagerV1: ()] => WlOutputs);
+
+/// 当前 Wayland 会话中的全部输出
+pub(super) fn wl_outputs() -> XCapResult<Vec<WlOutputInfo>> {
+    let conn = Connection::connect_to_env()?;
+    let (globals, mut event_queue) = registry_queue_init::<OutputsState>(&conn)?;
+
+    let mut state = OutputsState::default();
+    bind_outputs(&globals, &mut event_queue, &mut state)?;
+
+    Ok(state.outputs)
+}
@@ -0,0 +1,323 @@
+use image::{imageops, RgbaImage};
+use std::{
+    os::fd::{AsFd, FromRawFd, OwnedFd},
+    ptr, slice,
+};
+use wayland_client::{
+    protocol::{
+        wl_buffer::WlBuffer,
+        wl_output::Transform,
+        wl_shm::{Format, WlShm},
+        wl_shm_pool::WlShmPool,
+    },
+    Dispatch, QueueHandle,
+};
+
+use crate::{
+    error::{XCapError, XCapResult},
+    frame::{Frame, PixelFormat},
+};
+
+/// 合成器写入的 buffer 格式与大小
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) struct ShmFormat {
+    pub format: Format,
+    pub width: u32,
+    pub height: u32,
+    pub stride: u32,
+}
+
+impl ShmFormat {
+    /// 只接受每个像素 4 字节、8 位通道的格式
+    pub fn is_supported(format: Format) -> bool {
+        matches!(
+            format,
+            Format::Argb8888 | Format::Xrgb8888 | Format::Abgr8888 | Format::Xbgr8888
+        )
+    }
+
+    fn pixel_format(&self) -> XCapResult<PixelFormat> {
+        // wl_shm 的格式是小端序，Argb8888 在内存中是 B G R A
+        match self.format {
+            Format::Argb8888 => Ok(PixelFormat::Bgra8),
+            Format::Xrgb8888 => Ok(PixelFormat::Bgrx8),
+            Format::Abgr8888 => Ok(PixelFormat::Rgba8),
+            Format::Xbgr8888 => Ok(PixelFormat::Rgbx8),
+            format => Err(XCapError::new(format!(
+                "Unsupported wl_shm format {:?}",
+                format
+            ))),
+        }
+    }
+
+    /// 检查合成器给出的格式与大小，返回 buffer 的字节数
+    fn buffer_size(&self) -> XCapResult<usize> {
+        self.pixel_format()?;
+
+        let size = self.stride as usize * self.height as usize;
+        if (self.stride as usize) < self.width as usize * 4 || size > i32::MAX as usize {
+            return Err(XCapError::new(format!(
+                "Invalid wl_shm buffer: {}x{} with stride {}",
+                self.width, self.height, self.stride
+            )));
+        }
+
+        Ok(size)
+    }
+}
+
+/// memfd 上的 wl_shm buffer，Drop 时销毁 Wayland 对象并解除映射
+pub(super) struct ShmBuffer {
+    pub buffer: WlBuffer,
+    pool: WlShmPool,
+    addr: *mut libc::c_void,
+    size: usize,
+    shm_format: ShmFormat,
+    // 映射期间需要保持文件描述符打开
+    _fd: OwnedFd,
+}
+
+impl ShmBuffer {
+    pub fn new<D>(shm: &WlShm, qh: &QueueHandle<D>, shm_format: ShmFormat) -> XCapResult<ShmBuffer>
+    where
+        D: Dispatch<WlShmPool, ()> + Dispatch<WlBuffer, ()> + 'static,
+    {
+        let size = shm_format.buffer_size()?;
+
+        let fd = unsafe {
+            let fd = libc::memfd_create(c"xcap-wayland-shm".as_ptr(), libc::MFD_CLOEXEC);
+            if fd == -1 {
+                return Err(std::io::Error::last_os_error().into());
+            }
+
+            OwnedFd::from_raw_fd(fd)
+        };
+
+        let addr = unsafe {
+            use std::os::fd::AsRawFd;
+
+            if libc::ftruncate(fd.as_raw_fd(), size as libc::off_t) == -1 {
+                return Err(std::io::Error::last_os_error().into());
+            }
+
+            let addr = libc::mmap(
+                ptr::null_mut(),
+                size,
+                libc::PROT_READ | libc::PROT_WRITE,
+                libc::MAP_SHARED,
+                fd.as_raw_fd(),
+                0,
+            );
+            if addr == libc::MAP_FAILED {
+                return Err(std::io::Error::last_os_error().into());
+            }
+
+            addr
+        };
+
+        let pool = shm.create_pool(fd.as_fd(), size as i32, qh, ());
+        let buffer = pool.create_buffer(
+            0,
+            shm_format.width as i32,
+            shm_format.height as i32,
+            shm_format.stride as i32,
+            shm_format.format,
+            qh,
+            (),
+        );
+
+        Ok(ShmBuffer {
+            buffer,
+            pool,
+            addr,
+            size,
+            shm_format,
+            _fd: fd,
+        })
+    }
+
+    fn data(&self) -> &[u8] {
+        unsafe { slice::from_raw_parts(self.addr as *const u8, self.size) }
+    }
+
+    pub fn to_frame(&self, y_invert: bool, transform: Transform) -> XCapResult<Frame> {
+        to_frame(self.data(), self.shm_format, y_invert, transform)
+    }
+}
+
+impl Drop for ShmBuffer {
+    fn drop(&mut self) {
+        self.buffer.destroy();
+        self.pool.destroy();
+        unsafe {
+            libc::munmap(self.addr, self.size);
+        }
+    }
+}
+
+/// 复制为 Frame，按 y_invert 翻转行的顺序。显示器有 transform 时还原成逻辑方向，
+/// 这时会转换为 RGBA
+fn to_frame(
+    data: &[u8],
+    shm_format: ShmFormat,
+    y_invert: bool,
+    transform: Transform,
+) -> XCapResult<Frame> {
+    let ShmFormat {
+        width,
+        height,
+        stride,
+        ..
+    } = shm_format;
+    let pixel_format = shm_format.pixel_format()?;
+    let data = &data[..shm_format.buffer_size()?.min(data.len())];
+
+    let data = if y_invert && stride > 0 {
+        data.chunks_exact(stride as usize)
+            .rev()
+            .flatten()
+            .copied()
+            .collect()
+    } else {
+        data.to_vec()
+    };
+
+    let frame = Frame::from_raw(width, height, stride, pixel_format, data)?;
+    if transform == Transform::Normal {
+        return Ok(frame);
+    }
+
+    let rgba_image = frame.into_rgba_image()?;
+
+    Ok(Frame::from_rgba_image(apply_transform(
+        rgba_image, transform,
+    )))
+}
+
+/// buffer 是显示器面板的方向，transform 描述了合成器显示时做的变换，
+/// 其中的角度是逆时针方向
+fn apply_transform(rgba_image: RgbaImage, transform: Transform) -> RgbaImage {
+    let rgba_image = match transform {
+        Transform::Flipped
+        | Transform::Flipped90
+        | Transform::Flipped180
+        | Transform::Flipped270 => imageops::flip_horizontal(&rgba_image),
+        _ => rgba_image,
+    };
+
+    match transform {
+        Transform::_90 | Transform::Flipped90 => imageops::rotate270(&rgba_image),
+        Transform::_180 | Transform::Flipped180 => imageops::rotate180(&rgba_image),
+        Transform::_270 | Transform::Flipped270 => imageops::rotate90(&rgba_image),
+        _ => rgba_image,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn shm_format(format: Format, width: u32, height: u32, stride: u32) -> ShmFormat {
+        ShmFormat {
+            format,
+            width,
+            height,
+            stride,
+        }
+    }
+
+    #[test]
+    fn formats_to_rgba() {
+        // 1x1，内存中的 4 个字节依次是 1 2 3 4
+        let cases = [
+            (Format::Argb8888, [3, 2, 1, 4]),
+            (Format::Xrgb8888, [3, 2, 1, 255]),
+            (Format::Abgr8888, [1, 2, 3, 4]),
+            (Format::Xbgr8888, [1, 2, 3, 255]),
+        ];
+
+        for (format, expected) in cases {
+            let frame = to_frame(
+                &[1, 2, 3, 4],
+                shm_format(format, 1, 1, 4),
+                false,
+                Transform::Normal,
+            )
+            .unwrap();
+
+            assert_eq!(frame.into_rgba_image().unwrap().get_pixel(0, 0).0, expected);
+        }
+    }
+
+    #[test]
+    fn unsupported_format() {
+        let result = to_frame(
+            &[0; 4],
+            shm_format(Format::Rgb565, 1, 1, 4),
+            false,
+            Transform::Normal,
+        );
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn invalid_stride() {
+        assert!(shm_format(Format::Argb8888, 4, 2, 12)
+            .buffer_size()
+            .is_err());
+        assert!(shm_format(Format::Argb8888, 4, 2, 16).buffer_size().is_ok());
+
+        let result = to_frame(
+            &[0; 24],
+            shm_format(Format::Argb8888, 4, 2, 12),
+            false,
+            Transform::Normal,
+        );
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn padded_rows_and_y_invert() {
+        // 1x2，每行末尾有 4 字节填充
+        let data = [1, 2, 3, 4, 0, 0, 0, 0, 5, 6, 7, 8, 0, 0, 0, 0];
+        let format = shm_format(Format::Abgr8888, 1, 2, 8);
+
+        let rgba_image = to_frame(&data, format, false, Transform::Normal)
+            .unwrap()
+            .into_rgba_image()
+            .unwrap();
+        assert_eq!(rgba_image.as_raw(), &[1, 2, 3, 4, 5, 6, 7, 8]);
+
+        let rgba_image = to_frame(&data, format, true, Transform::Normal)
+            .unwrap()
+            .into_rgba_image()
+            .unwrap();
+        assert_eq!(rgba_image.as_raw(), &[5, 6, 7, 8, 1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn transforms() {
+        // 2x1 的 buffer：左边红色，右边绿色
+        let red = [255, 0, 0, 255];
+        let green = [0, 255, 0, 255];
+        let rgba_image = RgbaImage::from_raw(2, 1, [red, green].concat()).unwrap();
+
+        let pixels = |transform| {
+            let rgba_image = apply_transform(rgba_image.clone(), transform);
+            let pixels: Vec<[u8; 4]> = rgba_image.pixels().map(|pixel| pixel.0).collect();
+
+            (rgba_image.dimensions(), pixels)
+        };
+
+        assert_eq!(pixels(Transform::Normal), ((2, 1), vec![red, green]));
+        assert_eq!(pixels(Transform::Flipped), ((2, 1), vec![green, red]));
+        assert_eq!(pixels(Transform::_180), ((2, 1), vec![green, red]));
+        assert_eq!(pixels(Transform::Flipped180), ((2, 1), vec![red, green]));
+        // _90 逆时针旋转 90 度还原，右边的像素转到上方
+        assert_eq!(pixels(Transform::_90), ((1, 2), vec![green, red]));
+        assert_eq!(pixels(Transform::_270), ((1, 2), vec![red, green]));
+        assert_eq!(pixels(Transform::Flipped90), ((1, 2), vec![red, green]));
+        assert_eq!(pixels(Transform::Flipped270), ((1, 2), vec![green, red]));
+    }
+}
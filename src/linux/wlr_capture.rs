@@ -0,0 +1,178 @@
+use wayland_client::{
+    delegate_dispatch, delegate_noop,
+    globals::{registry_queue_init, GlobalListContents},
+    protocol::{
+        wl_buffer::WlBuffer,
+        wl_output::WlOutput,
+        wl_registry::{self, WlRegistry},
+        wl_shm::WlShm,
+        wl_shm_pool::WlShmPool,
+    },
+    Connection, Dispatch, Proxy, QueueHandle, WEnum,
+};
+use wayland_protocols::xdg::xdg_output::zv1::client::{
+    zxdg_output_manager_v1::ZxdgOutputManagerV1, zxdg_output_v1::ZxdgOutputV1,
+};
+use wayland_protocols_wlr::screencopy::v1::client::{
+    zwlr_screencopy_frame_v1::{self, ZwlrScreencopyFrameV1},
+    zwlr_screencopy_manager_v1::ZwlrScreencopyManagerV1,
+};
+
+use crate::{
+    error::{XCapError, XCapResult},
+    frame::Frame,
+    rect::Rect,
+};
+
+use super::{
+    wayland_outputs::{
+        bind_outputs, find_output, to_output_region, WlOutputInfo, WlOutputs, WlOutputsHandler,
+    },
+    wayland_shm::{ShmBuffer, ShmFormat},
+};
+
+/// 一次 capture_output_region 请求的状态
+#[derive(Debug, Default)]
+struct FrameState {
+    shm_format: Option<ShmFormat>,
+    is_buffer_done: bool,
+    y_invert: bool,
+    is_ready: bool,
+    is_failed: bool,
+}
+
+#[derive(Debug, Default)]
+struct WlrState {
+    outputs: Vec<WlOutputInfo>,
+    frame: FrameState,
+}
+
+impl WlOutputsHandler for WlrState {
+    fn outputs(&mut self) -> &mut Vec<WlOutputInfo> {
+        &mut self.outputs
+    }
+}
+
+impl Dispatch<WlRegistry, GlobalListContents> for WlrState {
+    fn event(
+        _state: &mut Self,
+        _registry: &WlRegistry,
+        _event: wl_registry::Event,
+        _data: &GlobalListContents,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+    }
+}
+
+impl Dispatch<ZwlrScreencopyFrameV1, ()> for WlrState {
+    fn event(
+        state: &mut Self,
+        frame: &ZwlrScreencopyFrameV1,
+        event: zwlr_screencopy_frame_v1::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+        match event {
+            // 每种支持的 buffer 格式发送一次，使用第一个能转换的格式
+            zwlr_screencopy_frame_v1::Event::Buffer {
+                format: WEnum::Value(format),
+                width,
+                height,
+                stride,
+            } if state.frame.shm_format.is_none() && ShmFormat::is_supported(format) => {
+                state.frame.shm_format = Some(ShmFormat {
+                    format,
+                    width,
+                    height,
+                    stride,
+                });
+                // 版本 3 之前没有 buffer_done 事件，只会发送一次 buffer
+                if frame.version() < 3 {
+                    state.frame.is_buffer_done = true;
+                }
+            }
+            zwlr_screencopy_frame_v1::Event::BufferDone => state.frame.is_buffer_done = true,
+            zwlr_screencopy_frame_v1::Event::Flags { flags } => {
+                state.frame.y_invert = matches!(
+                    flags,
+                    WEnum::Value(flags) if flags.contains(zwlr_screencopy_frame_v1::Flags::YInvert)
+                );
+            }
+            zwlr_screencopy_frame_v1::Event::Ready { .. } => state.frame.is_ready = true,
+            zwlr_screencopy_frame_v1::Event::Failed => state.frame.is_failed = true,
+            _ => {}
+        }
+    }
+}
+
+delegate_dispatch!(WlrState: [WlOutput: usize] => WlOutputs);
+delegate_dispatch!(WlrState: [ZxdgOutputV1: usize] => WlOutputs);
+delegate_dispatch!(WlrState: [ZxdgOutputManagerV1: ()] => WlOutputs);
+delegate_noop!(WlrState: ignore WlShm);
+delegate_noop!(WlrState: WlShmPool);
+delegate_noop!(WlrState: ignore WlBuffer);
+delegate_noop!(WlrState: ZwlrScreencopyManagerV1);
+
+/// 通过 wlroots 的 screencopy 协议截取显示器上的区域，
+/// `monitor` 是 `Monitor` 的逻辑坐标，`region` 相对于显示器左上角
+pub fn wlr_capture(
+    monitor_name: &str,
+    monitor: Rect,
+    region: Rect,
+    overlay_cursor: bool,
+) -> XCapResult<Frame> {
+    let conn = Connection::connect_to_env()?;
+    let (globals, mut event_queue) = registry_queue_init::<WlrState>(&conn)?;
+    let qh = event_queue.handle();
+
+    let screencopy_manager: ZwlrScreencopyManagerV1 = globals.bind(&qh, 1..=3, ())?;
+    let shm: WlShm = globals.bind(&qh, 1..=1, ())?;
+
+    let mut state = WlrState::default();
+    bind_outputs(&globals, &mut event_queue, &mut state)?;
+
+    let output_info = find_output(&state.outputs, monitor_name, &monitor)
+        .ok_or_else(|| XCapError::new("Not found wayland output for monitor"))?
+        .clone();
+    let output_region = to_output_region(&output_info, &monitor, &region);
+
+    let frame = screencopy_manager.capture_output_region(
+        overlay_cursor as i32,
+        &output_info.output,
+        output_region.x,
+        output_region.y,
+        output_region.width as i32,
+        output_region.height as i32,
+        &qh,
+        (),
+    );
+
+    while !state.frame.is_buffer_done && !state.frame.is_failed {
+        event_queue.blocking_dispatch(&mut state)?;
+    }
+
+    let shm_format = match state.frame.shm_format {
+        Some(shm_format) if !state.frame.is_failed => shm_format,
+        _ => {
+            frame.destroy();
+            return Err(XCapError::new("Screencopy has no supported wl_shm format"));
+        }
+    };
+
+    let shm_buffer = ShmBuffer::new(&shm, &qh, shm_format)?;
+    frame.copy(&shm_buffer.buffer);
+
+    while !state.frame.is_ready && !state.frame.is_failed {
+        event_queue.blocking_dispatch(&mut state)?;
+    }
+
+    frame.destroy();
+
+    if state.frame.is_failed {
+        return Err(XCapError::new("Screencopy failed"));
+    }
+
+    shm_buffer.to_frame(state.frame.y_invert, output_info.transform)
+}
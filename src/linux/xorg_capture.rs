@@ -1,88 +1,206 @@
-use image::RgbaImage;
+use std::{ptr, slice};
 use xcb::{
-    x::{Drawable, GetImage, ImageFormat, ImageOrder, Window},
-    Connection,
+    shm,
+    x::{Drawable, GetImage, ImageFormat, Visualid},
+    Connection, Extension,
 };
 
-use crate::error::{XCapError, XCapResult};
+use crate::{
+    error::{XCapError, XCapResult},
+    frame::{Frame, PixelFormat},
+    frame_buffer::FrameBuffer,
+};
+
+use super::xorg_convert::{PixelConverter, PixelLayout};
+
+/// System V 共享内存段，Drop 时从当前进程中分离，从 X server 中分离需要调用 `detach`
+pub(super) struct ShmSegment {
+    seg: shm::Seg,
+    addr: *mut libc::c_void,
+    pub size: usize,
+}
+
+// 共享内存映射在整个进程中有效，可以在线程之间移动
+unsafe impl Send for ShmSegment {}
+
+impl ShmSegment {
+    pub fn new(conn: &Connection, size: usize) -> XCapResult<ShmSegment> {
+        unsafe {
+            let shmid = libc::shmget(libc::IPC_PRIVATE, size, libc::IPC_CREAT | 0o600);
+            if shmid == -1 {
+                return Err(std::io::Error::last_os_error().into());
+            }
+
+            let addr = libc::shmat(shmid, ptr::null(), 0);
+            // 标记删除，所有进程分离后由内核回收，避免泄漏
+            libc::shmctl(shmid, libc::IPC_RMID, ptr::null_mut());
+
+            if addr as isize == -1 {
+                return Err(std::io::Error::last_os_error().into());
+            }
+
+            let seg = conn.generate_id();
+            let attach_result = conn.send_and_check_request(&shm::Attach {
+                shmseg: seg,
+                shmid: shmid as u32,
+                read_only: false,
+            });
+
+            // 远程连接时 X server 无法访问本机共享内存，Attach 会失败
+            if let Err(err) = attach_result {
+                libc::shmdt(addr);
+                return Err(err.into());
+            }
+
+            Ok(ShmSegment { seg, addr, size })
+        }
+    }
+
+    fn data(&self) -> &[u8] {
+        unsafe { slice::from_raw_parts(self.addr as *const u8, self.size) }
+    }
+
+    /// 连接断开时 X server 会自动分离，长期使用的连接需要手动分离
+    pub fn detach(self, conn: &Connection) {
+        let _ = conn.send_and_check_request(&shm::Detach { shmseg: self.seg });
+    }
+}
+
+impl Drop for ShmSegment {
+    fn drop(&mut self) {
+        unsafe {
+            libc::shmdt(self.addr);
+        }
+    }
+}
 
-fn get_pixel8_rgba(
+/// 按 visual 把 ZPixmap 数据转换为 RGBA，写入 frame_buffer 中
+pub(super) fn convert_into(
+    conn: &Connection,
     bytes: &[u8],
-    x: u32,
-    y: u32,
+    depth: u8,
+    visual: Visualid,
     width: u32,
-    bits_per_pixel: u32,
-    bit_order: ImageOrder,
-) -> (u8, u8, u8, u8) {
-    let index = ((y * width + x) * bits_per_pixel / 8) as usize;
-
-    let pixel = if bit_order == ImageOrder::LsbFirst {
-        bytes[index]
-    } else {
-        bytes[index] & 7 << 4 | bytes[index] >> 4
-    };
-
-    let r = (pixel >> 6) as f32 / 3.0 * 255.0;
-    let g = ((pixel >> 2) & 7) as f32 / 7.0 * 255.0;
-    let b = (pixel & 3) as f32 / 3.0 * 255.0;
+    height: u32,
+    frame_buffer: &mut FrameBuffer,
+) -> XCapResult<()> {
+    let layout = PixelLayout::new(conn.get_setup(), depth, visual)?;
+    let pixel_converter = PixelConverter::new(conn, layout)?;
 
-    (r as u8, g as u8, b as u8, 255)
+    pixel_converter.convert(
+        bytes,
+        width,
+        height,
+        frame_buffer.resize(width, height),
+        width * 4,
+    )
 }
 
-fn get_pixel16_rgba(
+/// 不做像素转换，按 X server 的内存布局生成 Frame，没有对应 PixelFormat 的格式才转换为 RGBA
+pub(super) fn zpixmap_to_frame(
+    conn: &Connection,
     bytes: &[u8],
-    x: u32,
-    y: u32,
+    depth: u8,
+    visual: Visualid,
     width: u32,
-    bits_per_pixel: u32,
-    bit_order: ImageOrder,
-) -> (u8, u8, u8, u8) {
-    let index = ((y * width + x) * bits_per_pixel / 8) as usize;
-
-    let pixel = if bit_order == ImageOrder::LsbFirst {
-        bytes[index] as u16 | (bytes[index + 1] as u16) << 8
-    } else {
-        (bytes[index] as u16) << 8 | bytes[index + 1] as u16
+    height: u32,
+) -> XCapResult<Frame> {
+    let native_layout = PixelLayout::new(conn.get_setup(), depth, visual)
+        .ok()
+        .and_then(|layout| Some((layout, layout.pixel_format()?)));
+
+    let Some((layout, pixel_format)) = native_layout else {
+        let mut frame_buffer = FrameBuffer::new();
+        convert_into(conn, bytes, depth, visual, width, height, &mut frame_buffer)?;
+
+        return Ok(Frame::from_rgba_image(frame_buffer.into_rgba_image()?));
     };
 
-    let r = (pixel >> 11) as f32 / 31.0 * 255.0;
-    let g = ((pixel >> 5) & 63) as f32 / 63.0 * 255.0;
-    let b = (pixel & 31) as f32 / 31.0 * 255.0;
+    let stride = layout.stride(width);
+    let size = (stride * height) as usize;
+    if bytes.len() < size {
+        return Err(XCapError::new(format!(
+            "Image data too short: {} < {}",
+            bytes.len(),
+            size
+        )));
+    }
+
+    let mut data = bytes[..size].to_vec();
+    // 深度 30 的最高 2 位不属于任何颜色通道，X server 不保证其内容，设置为不透明
+    if pixel_format == PixelFormat::Rgb10A2 {
+        for pixel in data.chunks_exact_mut(4) {
+            pixel[3] |= 0xc0;
+        }
+    }
 
-    (r as u8, g as u8, b as u8, 255)
+    Frame::from_raw(width, height, stride, pixel_format, data)
 }
 
-fn get_pixel24_32_rgba(
-    bytes: &[u8],
-    x: u32,
-    y: u32,
-    width: u32,
-    bits_per_pixel: u32,
-    bit_order: ImageOrder,
-) -> (u8, u8, u8, u8) {
-    let index = ((y * width + x) * bits_per_pixel / 8) as usize;
-
-    if bit_order == ImageOrder::LsbFirst {
-        (bytes[index + 2], bytes[index + 1], bytes[index], 255)
-    } else {
-        (bytes[index], bytes[index + 1], bytes[index + 2], 255)
+/// MIT-SHM 只能用于本机连接，需要以可选扩展 `Extension::Shm` 建立连接
+pub(super) fn query_shm(conn: &Connection) -> XCapResult<()> {
+    let is_shm_active = conn
+        .active_extensions()
+        .any(|extension| extension == Extension::Shm);
+
+    if !is_shm_active {
+        return Err(XCapError::new("MIT-SHM extension not available"));
     }
+
+    let query_version_cookie = conn.send_request(&shm::QueryVersion {});
+    conn.wait_for_reply(query_version_cookie)?;
+
+    Ok(())
 }
 
-pub fn xorg_capture(
-    window: Window,
+/// 通过已经 Attach 的共享内存段读取 ZPixmap 图像，共享内存段至少需要 width * height * 4 字节。
+/// `read` 接收图像数据与深度，数据只在共享内存段下次使用前有效
+#[allow(clippy::too_many_arguments)]
+pub(super) fn shm_get_image<T>(
+    conn: &Connection,
+    shm_segment: &ShmSegment,
+    drawable: Drawable,
     x: i32,
     y: i32,
     width: u32,
     height: u32,
-) -> XCapResult<RgbaImage> {
-    let (conn, _) = Connection::connect(None)?;
+    read: impl FnOnce(&[u8], u8, Visualid) -> XCapResult<T>,
+) -> XCapResult<T> {
+    let get_image_cookie = conn.send_request(&shm::GetImage {
+        drawable,
+        x: x as i16,
+        y: y as i16,
+        width: width as u16,
+        height: height as u16,
+        plane_mask: u32::MAX,
+        format: ImageFormat::ZPixmap as u8,
+        shmseg: shm_segment.seg,
+        offset: 0,
+    });
 
-    let setup = conn.get_setup();
+    let get_image_reply = conn.wait_for_reply(get_image_cookie)?;
+    let size = (get_image_reply.size() as usize).min(shm_segment.size);
+
+    read(
+        &shm_segment.data()[..size],
+        get_image_reply.depth(),
+        get_image_reply.visual(),
+    )
+}
 
+/// 通过 GetImage 请求读取 ZPixmap 图像，`read` 接收图像数据与深度
+pub(super) fn get_image<T>(
+    conn: &Connection,
+    drawable: Drawable,
+    x: i32,
+    y: i32,
+    width: u32,
+    height: u32,
+    read: impl FnOnce(&[u8], u8, Visualid) -> XCapResult<T>,
+) -> XCapResult<T> {
     let get_image_cookie = conn.send_request(&GetImage {
         format: ImageFormat::ZPixmap,
-        drawable: Drawable::Window(window),
+        drawable,
         x: x as i16,
         y: y as i16,
         width: width as u16,
@@ -91,39 +209,118 @@ pub fn xorg_capture(
     });
 
     let get_image_reply = conn.wait_for_reply(get_image_cookie)?;
-    let bytes = get_image_reply.data();
-    let depth = get_image_reply.depth();
-
-    let pixmap_format = setup
-        .pixmap_formats()
-        .iter()
-        .find(|item| item.depth() == depth)
-        .ok_or(XCapError::new("Not found pixmap format"))?;
-
-    let bits_per_pixel = pixmap_format.bits_per_pixel() as u32;
-    let bit_order = setup.bitmap_format_bit_order();
-
-    let get_pixel_rgba = match depth {
-        8 => get_pixel8_rgba,
-        16 => get_pixel16_rgba,
-        24 => get_pixel24_32_rgba,
-        32 => get_pixel24_32_rgba,
-        _ => return Err(XCapError::new(format!("Unsupported {} depth", depth))),
-    };
 
-    let mut rgba = vec![0u8; (width * height * 4) as usize];
-    for y in 0..height {
-        for x in 0..width {
-            let index = ((y * width + x) * 4) as usize;
-            let (r, g, b, a) = get_pixel_rgba(bytes, x, y, width, bits_per_pixel, bit_order);
+    read(
+        get_image_reply.data(),
+        get_image_reply.depth(),
+        get_image_reply.visual(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::platform::test_server::start_xvfb;
+    use crate::platform::xorg_connection::XorgConnection;
+    use xcb::x::{AllocColor, ChangeGc, CreateGc, Gc, PolyFillRectangle, Rectangle};
+
+    /// 在 root window 上依次画 8x8 的色块，截图后与 X server 实际分配的颜色比较
+    fn check_colors(depth: u8) {
+        let xvfb = start_xvfb(depth);
+        let display = format!(":{}", xvfb.address);
+
+        let (conn, screen_num) =
+            Connection::connect_with_extensions(Some(&display), &[], &[Extension::Shm]).unwrap();
+        let conn = XorgConnection::new(conn, screen_num);
+        let screen = conn.get_setup().roots().nth(screen_num as usize).unwrap();
+        let root = Drawable::Window(screen.root());
+        let colors = [
+            (0xffff, 0, 0),
+            (0, 0xffff, 0),
+            (0, 0, 0xffff),
+            (0xffff, 0xffff, 0xffff),
+            (0, 0, 0),
+            (0x8000, 0x4000, 0xc000),
+        ];
+
+        let gc = conn.generate_id();
+        conn.send_and_check_request(&CreateGc {
+            cid: gc,
+            drawable: root,
+            value_list: &[],
+        })
+        .unwrap();
+
+        let mut expected = Vec::new();
+        for (index, &(red, green, blue)) in colors.iter().enumerate() {
+            let alloc_color_cookie = conn.send_request(&AllocColor {
+                cmap: screen.default_colormap(),
+                red,
+                green,
+                blue,
+            });
+            let alloc_color_reply = conn.wait_for_reply(alloc_color_cookie).unwrap();
+
+            conn.send_and_check_request(&ChangeGc {
+                gc,
+                value_list: &[Gc::Foreground(alloc_color_reply.pixel())],
+            })
+            .unwrap();
+            conn.send_and_check_request(&PolyFillRectangle {
+                drawable: root,
+                gc,
+                rectangles: &[Rectangle {
+                    x: index as i16 * 8,
+                    y: 0,
+                    width: 8,
+                    height: 8,
+                }],
+            })
+            .unwrap();
+
+            expected.push([
+                (alloc_color_reply.red() >> 8) as u8,
+                (alloc_color_reply.green() >> 8) as u8,
+                (alloc_color_reply.blue() >> 8) as u8,
+            ]);
+        }
+
+        let width = colors.len() as u32 * 8;
+        let rgba_image = conn.capture(root, 0, 0, width, 8).unwrap();
 
-            rgba[index] = r;
-            rgba[index + 1] = g;
-            rgba[index + 2] = b;
-            rgba[index + 3] = a;
+        for (index, expected) in expected.iter().enumerate() {
+            let rgba = rgba_image.get_pixel(index as u32 * 8 + 4, 4).0;
+
+            // X server 与 xcap 把通道扩展到 16 位和 8 位时的舍入可能相差 1
+            for channel in 0..3 {
+                assert!(
+                    rgba[channel].abs_diff(expected[channel]) <= 1,
+                    "depth {} color {}: {:?} != {:?}",
+                    depth,
+                    index,
+                    rgba,
+                    expected
+                );
+            }
+            assert_eq!(rgba[3], 255);
         }
     }
 
-    RgbaImage::from_raw(width, height, rgba)
-        .ok_or_else(|| XCapError::new("RgbaImage::from_raw failed"))
+    #[test]
+    #[ignore = "needs Xvfb"]
+    fn xvfb_depth_8_pseudo_color() {
+        check_colors(8);
+    }
+
+    #[test]
+    #[ignore = "needs Xvfb"]
+    fn xvfb_depth_16_true_color() {
+        check_colors(16);
+    }
+
+    #[test]
+    #[ignore = "needs Xvfb"]
+    fn xvfb_depth_24_true_color() {
+        check_colors(24);
+    }
 }
@@ -0,0 +1,323 @@
+use image::RgbaImage;
+use std::{
+    collections::HashMap,
+    fmt,
+    ops::Deref,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+};
+use xcb::{
+    x::{Atom, Drawable, InternAtom, Visualid, ATOM_NONE},
+    Connection, Extension,
+};
+
+use crate::{
+    capture_options::X11CaptureMethod,
+    error::{XCapError, XCapResult},
+    frame::Frame,
+    frame_buffer::FrameBuffer,
+    rect::Rect,
+};
+
+use super::{
+    xorg_capture::{
+        convert_into, get_image, query_shm, shm_get_image, zpixmap_to_frame, ShmSegment,
+    },
+    xorg_convert::{PixelConverter, PixelLayout},
+};
+
+/// 截图 API 共用的连接，见 `XorgConnection::shared`
+static SHARED_CONNECTION: Mutex<Option<Arc<XorgConnection>>> = Mutex::new(None);
+
+/// 可以复用的 X 连接，缓存 atom 与 MIT-SHM 共享内存段，避免每次截图都重新握手
+pub(crate) struct XorgConnection {
+    conn: Connection,
+    screen_num: i32,
+    atoms: Mutex<HashMap<String, Atom>>,
+    shm_segment: Mutex<Option<ShmSegment>>,
+    is_shm_available: AtomicBool,
+    last_capture_method: Mutex<Option<X11CaptureMethod>>,
+}
+
+impl fmt::Debug for XorgConnection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("XorgConnection")
+            .field("screen_num", &self.screen_num)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Deref for XorgConnection {
+    type Target = Connection;
+
+    fn deref(&self) -> &Connection {
+        &self.conn
+    }
+}
+
+impl XorgConnection {
+    pub fn new(conn: Connection, screen_num: i32) -> XorgConnection {
+        let is_shm_available = query_shm(&conn).is_ok();
+
+        XorgConnection {
+            conn,
+            screen_num,
+            atoms: Mutex::new(HashMap::new()),
+            shm_segment: Mutex::new(None),
+            is_shm_available: AtomicBool::new(is_shm_available),
+            last_capture_method: Mutex::new(None),
+        }
+    }
+
+    /// 连接时启用截图用到的可选扩展：MIT-SHM、Composite 与 X-Resource
+    pub fn connect() -> XCapResult<XorgConnection> {
+        XorgConnection::connect_with_extensions(&[])
+    }
+
+    /// 在可选扩展之外，还要求 X server 支持 `mandatory` 中的扩展
+    pub fn connect_with_extensions(mandatory: &[Extension]) -> XCapResult<XorgConnection> {
+        let (conn, screen_num) = Connection::connect_with_extensions(
+            None,
+            mandatory,
+            &[Extension::Shm, Extension::Composite, Extension::Res],
+        )?;
+
+        Ok(XorgConnection::new(conn, screen_num))
+    }
+
+    /// `Monitor::capture_image` 等截图 API 共用的连接，第一次截图时建立，之后保留连接与共享内存段。
+    /// 连接出错（例如 X server 重启）后重新建立
+    pub fn shared() -> XCapResult<Arc<XorgConnection>> {
+        let mut shared_connection = SHARED_CONNECTION
+            .lock()
+            .map_err(|_| XCapError::new("Get shared connection lock failed"))?;
+
+        if let Some(conn) = shared_connection.as_ref() {
+            if conn.has_error().is_ok() {
+                return Ok(conn.clone());
+            }
+        }
+
+        let conn = Arc::new(XorgConnection::connect()?);
+        *shared_connection = Some(conn.clone());
+
+        Ok(conn)
+    }
+
+    /// 共用连接上一次截图实际使用的方式，还没有截图时为 None
+    pub fn shared_last_capture_method() -> Option<X11CaptureMethod> {
+        SHARED_CONNECTION
+            .lock()
+            .ok()?
+            .as_ref()
+            .and_then(|conn| conn.last_capture_method())
+    }
+
+    pub fn screen_num(&self) -> i32 {
+        self.screen_num
+    }
+
+    /// 上一次截图实际使用的方式，还没有截图时为 None
+    pub fn last_capture_method(&self) -> Option<X11CaptureMethod> {
+        self.last_capture_method
+            .lock()
+            .ok()
+            .and_then(|last_capture_method| *last_capture_method)
+    }
+
+    fn set_last_capture_method(&self, method: X11CaptureMethod) {
+        log::debug!("X11 capture used {:?}", method);
+        if let Ok(mut last_capture_method) = self.last_capture_method.lock() {
+            *last_capture_method = Some(method);
+        }
+    }
+
+    /// atom 在 X server 的生命周期内不会变化，只查询一次
+    pub fn atom(&self, name: &str) -> XCapResult<Atom> {
+        let mut atoms = self
+            .atoms
+            .lock()
+            .map_err(|_| XCapError::new("Get atoms lock failed"))?;
+
+        let atom = match atoms.get(name) {
+            Some(&atom) => atom,
+            None => {
+                let atom_cookie = self.conn.send_request(&InternAtom {
+                    only_if_exists: true,
+                    name: name.as_bytes(),
+                });
+                let atom = self.conn.wait_for_reply(atom_cookie)?.atom();
+                atoms.insert(name.to_string(), atom);
+
+                atom
+            }
+        };
+
+        if atom == ATOM_NONE {
+            return Err(XCapError::new(format!("{} not supported", name)));
+        }
+
+        Ok(atom)
+    }
+
+    pub fn capture(
+        &self,
+        drawable: Drawable,
+        x: i32,
+        y: i32,
+        width: u32,
+        height: u32,
+    ) -> XCapResult<RgbaImage> {
+        let mut frame_buffer = FrameBuffer::new();
+        self.capture_into(drawable, x, y, width, height, &mut frame_buffer)?;
+
+        frame_buffer.into_rgba_image()
+    }
+
+    /// 截图写入 frame_buffer
+    pub fn capture_into(
+        &self,
+        drawable: Drawable,
+        x: i32,
+        y: i32,
+        width: u32,
+        height: u32,
+        frame_buffer: &mut FrameBuffer,
+    ) -> XCapResult<()> {
+        self.read_image(drawable, x, y, width, height, |bytes, depth, visual| {
+            convert_into(
+                &self.conn,
+                bytes,
+                depth,
+                visual,
+                width,
+                height,
+                frame_buffer,
+            )
+        })
+    }
+
+    /// 截取 (x, y) 偏移 region 后的区域，直接写入 rgba_image 中 region 所在的位置
+    pub fn capture_into_image(
+        &self,
+        drawable: Drawable,
+        x: i32,
+        y: i32,
+        region: Rect,
+        rgba_image: &mut RgbaImage,
+    ) -> XCapResult<()> {
+        let bounds = Rect::new(0, 0, rgba_image.width(), rgba_image.height());
+        if bounds.intersection(&region) != Some(region) {
+            return Err(XCapError::InvalidRegion { region, bounds });
+        }
+
+        let stride = rgba_image.width() * 4;
+        let offset = (region.y as u32 * stride + region.x as u32 * 4) as usize;
+
+        self.read_image(
+            drawable,
+            x + region.x,
+            y + region.y,
+            region.width,
+            region.height,
+            |bytes, depth, visual| {
+                let layout = PixelLayout::new(self.conn.get_setup(), depth, visual)?;
+                PixelConverter::new(&self.conn, layout)?.convert(
+                    bytes,
+                    region.width,
+                    region.height,
+                    &mut rgba_image.as_mut()[offset..],
+                    stride,
+                )
+            },
+        )
+    }
+
+    /// 截图并保留 X server 的像素格式
+    pub fn capture_frame(
+        &self,
+        drawable: Drawable,
+        x: i32,
+        y: i32,
+        width: u32,
+        height: u32,
+    ) -> XCapResult<Frame> {
+        self.read_image(drawable, x, y, width, height, |bytes, depth, visual| {
+            zpixmap_to_frame(&self.conn, bytes, depth, visual, width, height)
+        })
+    }
+
+    /// 优先使用 MIT-SHM 读取 ZPixmap 图像，共享内存段不够大时才重新分配
+    fn read_image<T>(
+        &self,
+        drawable: Drawable,
+        x: i32,
+        y: i32,
+        width: u32,
+        height: u32,
+        mut read: impl FnMut(&[u8], u8, Visualid) -> XCapResult<T>,
+    ) -> XCapResult<T> {
+        if self.is_shm_available.load(Ordering::Relaxed) {
+            match self.shm_read_image(drawable, x, y, width, height, &mut read) {
+                Ok(result) => {
+                    self.set_last_capture_method(X11CaptureMethod::Shm);
+                    return Ok(result);
+                }
+                Err(err) => log::debug!("MIT-SHM capture failed, fallback to GetImage: {}", err),
+            }
+        }
+
+        let result = get_image(&self.conn, drawable, x, y, width, height, read)?;
+        self.set_last_capture_method(X11CaptureMethod::GetImage);
+
+        Ok(result)
+    }
+
+    fn shm_read_image<T>(
+        &self,
+        drawable: Drawable,
+        x: i32,
+        y: i32,
+        width: u32,
+        height: u32,
+        read: impl FnOnce(&[u8], u8, Visualid) -> XCapResult<T>,
+    ) -> XCapResult<T> {
+        let mut shm_segment = self
+            .shm_segment
+            .lock()
+            .map_err(|_| XCapError::new("Get shm segment lock failed"))?;
+
+        // 按每像素最多 4 字节分配，足够容纳任意深度的 ZPixmap
+        let size = (width * height * 4) as usize;
+        if !matches!(&*shm_segment, Some(shm_segment) if shm_segment.size >= size) {
+            if let Some(old_shm_segment) = shm_segment.take() {
+                old_shm_segment.detach(&self.conn);
+            }
+
+            match ShmSegment::new(&self.conn, size) {
+                Ok(new_shm_segment) => *shm_segment = Some(new_shm_segment),
+                Err(err) => {
+                    // 远程连接无法使用共享内存，之后直接使用 GetImage
+                    self.is_shm_available.store(false, Ordering::Relaxed);
+                    return Err(err);
+                }
+            }
+        }
+
+        let shm_segment = shm_segment
+            .as_ref()
+            .ok_or_else(|| XCapError::new("Not found shm segment"))?;
+
+        shm_get_image(&self.conn, shm_segment, drawable, x, y, width, height, read)
+    }
+}
+
+impl Drop for XorgConnection {
+    fn drop(&mut self) {
+        if let Some(shm_segment) = self.shm_segment.get_mut().ok().and_then(Option::take) {
+            shm_segment.detach(&self.conn);
+        }
+    }
+}
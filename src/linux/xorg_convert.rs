@@ -0,0 +1,736 @@
+use xcb::{
+    x::{Colormap, ImageOrder, QueryColors, Setup, VisualClass, Visualid, Visualtype},
+    Connection,
+};
+
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+
+use crate::{
+    error::{XCapError, XCapResult},
+    frame::PixelFormat,
+};
+
+/// ZPixmap 图像的内存布局，TrueColor 与 DirectColor 的颜色通道位置来自 Visualtype 的掩码，
+/// 其它 visual 的像素值是 colormap 的索引
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) struct PixelLayout {
+    pub bits_per_pixel: u32,
+    pub scanline_pad: u32,
+    pub byte_order: ImageOrder,
+    pub visual_class: VisualClass,
+    /// visual 所在屏幕的默认 colormap
+    pub colormap: Colormap,
+    pub colormap_entries: u16,
+    pub red_mask: u32,
+    pub green_mask: u32,
+    pub blue_mask: u32,
+    /// 只有深度 32 的 visual 才有 alpha 通道，其它深度中多余的位内容不确定
+    pub alpha_mask: u32,
+}
+
+/// 按 visual id 查找，pixmap 没有 visual 时优先使用同一深度的 TrueColor visual。
+/// 同时返回 visual 所在屏幕的默认 colormap
+fn find_visual(setup: &Setup, depth: u8, visual: Visualid) -> Option<(Colormap, &Visualtype)> {
+    let visuals = || {
+        setup.roots().flat_map(|screen| {
+            screen
+                .allowed_depths()
+                .filter(|allowed_depth| allowed_depth.depth() == depth)
+                .flat_map(|allowed_depth| allowed_depth.visuals())
+                .map(|visualtype| (screen.default_colormap(), visualtype))
+        })
+    };
+
+    visuals()
+        .find(|(_, visualtype)| visualtype.visual_id() == visual)
+        .or_else(|| visuals().find(|(_, visualtype)| visualtype.class() == VisualClass::TrueColor))
+        .or_else(|| visuals().next())
+}
+
+impl PixelLayout {
+    pub fn new(setup: &Setup, depth: u8, visual: Visualid) -> XCapResult<PixelLayout> {
+        let pixmap_format = setup
+            .pixmap_formats()
+            .iter()
+            .find(|item| item.depth() == depth)
+            .ok_or(XCapError::new("Not found pixmap format"))?;
+
+        let (colormap, visualtype) = find_visual(setup, depth, visual)
+            .ok_or_else(|| XCapError::new(format!("Not found visual for {} depth", depth)))?;
+
+        let bits_per_pixel = pixmap_format.bits_per_pixel() as u32;
+        let color_mask = visualtype.red_mask() | visualtype.green_mask() | visualtype.blue_mask();
+
+        Ok(PixelLayout {
+            bits_per_pixel,
+            scanline_pad: pixmap_format.scanline_pad() as u32,
+            byte_order: setup.image_byte_order(),
+            visual_class: visualtype.class(),
+            colormap,
+            colormap_entries: visualtype.colormap_entries(),
+            red_mask: visualtype.red_mask(),
+            green_mask: visualtype.green_mask(),
+            blue_mask: visualtype.blue_mask(),
+            alpha_mask: if depth == 32 && bits_per_pixel == 32 {
+                !color_mask
+            } else {
+                0
+            },
+        })
+    }
+
+    fn has_masks(&self) -> bool {
+        matches!(
+            self.visual_class,
+            VisualClass::TrueColor | VisualClass::DirectColor
+        )
+    }
+
+    /// 每行按 scanline_pad 位对齐后的字节数
+    pub fn stride(&self, width: u32) -> u32 {
+        (width * self.bits_per_pixel).div_ceil(self.scanline_pad) * self.scanline_pad / 8
+    }
+
+    /// 与公开的 PixelFormat 完全一致时可以直接使用 X server 的数据
+    pub fn pixel_format(&self) -> Option<PixelFormat> {
+        if self.byte_order != ImageOrder::LsbFirst || !self.has_masks() {
+            return None;
+        }
+
+        let masks = (
+            self.bits_per_pixel,
+            self.red_mask,
+            self.green_mask,
+            self.blue_mask,
+            self.alpha_mask,
+        );
+
+        match masks {
+            (16, 0xf800, 0x07e0, 0x001f, 0) => Some(PixelFormat::Rgb565),
+            (32, 0xff0000, 0x00ff00, 0x0000ff, 0) => Some(PixelFormat::Bgrx8),
+            (32, 0xff0000, 0x00ff00, 0x0000ff, 0xff000000) => Some(PixelFormat::Bgra8),
+            (32, 0x3ff00000, 0x000ffc00, 0x000003ff, 0) => Some(PixelFormat::Rgb10A2),
+            _ => None,
+        }
+    }
+}
+
+/// 把一个颜色通道的值缩放到 8 位，预先计算查找表
+#[derive(Debug, Clone)]
+struct Channel {
+    shift: u32,
+    mask: u32,
+    table: Vec<u8>,
+}
+
+impl Channel {
+    fn new(mask: u32) -> Option<Channel> {
+        if mask == 0 {
+            return None;
+        }
+
+        let mut shift = mask.trailing_zeros();
+        let mut bits = (mask >> shift).trailing_ones();
+        // 超过 10 位的通道只保留高位，查找表不会太大
+        if bits > 10 {
+            shift += bits - 10;
+            bits = 10;
+        }
+
+        let max = (1u32 << bits) - 1;
+        let table = (0..=max)
+            .map(|value| ((value * 255 + max / 2) / max) as u8)
+            .collect();
+
+        Some(Channel {
+            shift,
+            mask: max,
+            table,
+        })
+    }
+
+    #[inline(always)]
+    fn scale(&self, pixel: u32) -> u8 {
+        self.table[((pixel >> self.shift) & self.mask) as usize]
+    }
+}
+
+/// 读取 colormap 中的全部颜色。PseudoColor 的 colormap 可以被程序随时修改，每次截图都需要重新读取
+fn query_palette(conn: &Connection, layout: &PixelLayout) -> XCapResult<Vec<[u8; 4]>> {
+    let pixels: Vec<u32> = (0..layout.colormap_entries as u32).collect();
+    let query_colors_cookie = conn.send_request(&QueryColors {
+        cmap: layout.colormap,
+        pixels: &pixels,
+    });
+    let query_colors_reply = conn.wait_for_reply(query_colors_cookie)?;
+
+    let palette = query_colors_reply
+        .colors()
+        .iter()
+        .map(|rgb| {
+            [
+                (rgb.red() >> 8) as u8,
+                (rgb.green() >> 8) as u8,
+                (rgb.blue() >> 8) as u8,
+                255,
+            ]
+        })
+        .collect();
+
+    Ok(palette)
+}
+
+#[derive(Debug, Clone)]
+enum Colors {
+    Masks {
+        red: Channel,
+        green: Channel,
+        blue: Channel,
+        alpha: Option<Channel>,
+    },
+    /// 按像素值索引的颜色表，超出范围的像素为黑色
+    Palette(Vec<[u8; 4]>),
+}
+
+/// 按 PixelLayout 把 ZPixmap 数据转换为 RGBA8
+#[derive(Debug, Clone)]
+pub(super) struct PixelConverter {
+    layout: PixelLayout,
+    colors: Colors,
+}
+
+impl PixelConverter {
+    /// TrueColor 与 DirectColor 使用掩码，PseudoColor、StaticColor、GrayScale 与 StaticGray 查询 colormap
+    pub fn new(conn: &Connection, layout: PixelLayout) -> XCapResult<PixelConverter> {
+        if layout.has_masks() {
+            return PixelConverter::from_masks(layout);
+        }
+
+        PixelConverter::from_palette(layout, query_palette(conn, &layout)?)
+    }
+
+    fn check_bits_per_pixel(layout: &PixelLayout) -> XCapResult<()> {
+        if !matches!(layout.bits_per_pixel, 8 | 16 | 24 | 32) {
+            return Err(XCapError::new(format!(
+                "Unsupported {} bits per pixel",
+                layout.bits_per_pixel
+            )));
+        }
+
+        Ok(())
+    }
+
+    pub fn from_masks(layout: PixelLayout) -> XCapResult<PixelConverter> {
+        PixelConverter::check_bits_per_pixel(&layout)?;
+
+        let channel = |mask: u32| {
+            Channel::new(mask).ok_or_else(|| XCapError::new(format!("Invalid layout {:?}", layout)))
+        };
+
+        Ok(PixelConverter {
+            layout,
+            colors: Colors::Masks {
+                red: channel(layout.red_mask)?,
+                green: channel(layout.green_mask)?,
+                blue: channel(layout.blue_mask)?,
+                alpha: Channel::new(layout.alpha_mask),
+            },
+        })
+    }
+
+    pub fn from_palette(layout: PixelLayout, palette: Vec<[u8; 4]>) -> XCapResult<PixelConverter> {
+        PixelConverter::check_bits_per_pixel(&layout)?;
+
+        Ok(PixelConverter {
+            layout,
+            colors: Colors::Palette(palette),
+        })
+    }
+
+    /// 每种像素宽度与字节序单独展开循环，循环内没有分支，便于编译器向量化
+    #[inline(always)]
+    fn convert_pixels<const N: usize>(
+        src: &[u8],
+        dst: &mut [u8],
+        read: impl Fn(&[u8; N]) -> u32,
+        rgba: impl Fn(u32) -> [u8; 4],
+    ) {
+        for (src, dst) in src.chunks_exact(N).zip(dst.chunks_exact_mut(4)) {
+            // chunks_exact 保证长度为 N
+            let pixel = read(src.try_into().unwrap_or(&[0; N]));
+            dst.copy_from_slice(&rgba(pixel));
+        }
+    }
+
+    #[inline(always)]
+    fn read_pixels(&self, src: &[u8], dst: &mut [u8], rgba: impl Fn(u32) -> [u8; 4]) {
+        let is_lsb_first = self.layout.byte_order == ImageOrder::LsbFirst;
+
+        match self.layout.bits_per_pixel {
+            32 if is_lsb_first => {
+                Self::convert_pixels(src, dst, |p: &[u8; 4]| u32::from_le_bytes(*p), rgba)
+            }
+            32 => Self::convert_pixels(src, dst, |p: &[u8; 4]| u32::from_be_bytes(*p), rgba),
+            24 if is_lsb_first => Self::convert_pixels(
+                src,
+                dst,
+                |p: &[u8; 3]| u32::from_le_bytes([p[0], p[1], p[2], 0]),
+                rgba,
+            ),
+            24 => Self::convert_pixels(
+                src,
+                dst,
+                |p: &[u8; 3]| u32::from_be_bytes([0, p[0], p[1], p[2]]),
+                rgba,
+            ),
+            16 if is_lsb_first => {
+                Self::convert_pixels(src, dst, |p: &[u8; 2]| u16::from_le_bytes(*p) as u32, rgba)
+            }
+            16 => Self::convert_pixels(src, dst, |p: &[u8; 2]| u16::from_be_bytes(*p) as u32, rgba),
+            // 8 位像素正好一个字节，与字节序无关
+            _ => Self::convert_pixels(src, dst, |p: &[u8; 1]| p[0] as u32, rgba),
+        }
+    }
+
+    fn convert_row(&self, src: &[u8], dst: &mut [u8]) {
+        let layout = &self.layout;
+
+        match &self.colors {
+            // 最常见的 BGRX 布局只需要交换字节
+            Colors::Masks { .. }
+                if layout.bits_per_pixel == 32
+                    && layout.byte_order == ImageOrder::LsbFirst
+                    && (layout.red_mask, layout.green_mask, layout.blue_mask)
+                        == (0xff0000, 0x00ff00, 0x0000ff)
+                    && matches!(layout.alpha_mask, 0 | 0xff000000) =>
+            {
+                let has_alpha = layout.alpha_mask != 0;
+                for (src, dst) in src.chunks_exact(4).zip(dst.chunks_exact_mut(4)) {
+                    dst[0] = src[2];
+                    dst[1] = src[1];
+                    dst[2] = src[0];
+                    dst[3] = if has_alpha { src[3] } else { 255 };
+                }
+            }
+            Colors::Masks {
+                red,
+                green,
+                blue,
+                alpha,
+            } => self.read_pixels(src, dst, |pixel| {
+                [
+                    red.scale(pixel),
+                    green.scale(pixel),
+                    blue.scale(pixel),
+                    alpha.as_ref().map_or(255, |alpha| alpha.scale(pixel)),
+                ]
+            }),
+            Colors::Palette(palette) => self.read_pixels(src, dst, |pixel| {
+                palette
+                    .get(pixel as usize)
+                    .copied()
+                    .unwrap_or([0, 0, 0, 255])
+            }),
+        }
+    }
+
+    /// 转换 width x height 的图像，`dst` 的行间隔为 dst_stride 字节，可以大于 width * 4
+    pub fn convert(
+        &self,
+        src: &[u8],
+        width: u32,
+        height: u32,
+        dst: &mut [u8],
+        dst_stride: u32,
+    ) -> XCapResult<()> {
+        let stride = self.layout.stride(width) as usize;
+        let src_row_size = (width * self.layout.bits_per_pixel / 8) as usize;
+        let dst_stride = dst_stride as usize;
+        let dst_row_size = (width * 4) as usize;
+        let (src_size, dst_size) = match height {
+            0 => (0, 0),
+            height => (
+                (height as usize - 1) * stride + src_row_size,
+                (height as usize - 1) * dst_stride + dst_row_size,
+            ),
+        };
+
+        if src.len() < src_size {
+            return Err(XCapError::new(format!(
+                "Image data too short: {} < {}",
+                src.len(),
+                src_size
+            )));
+        }
+
+        if dst_stride < dst_row_size || dst.len() < dst_size {
+            return Err(XCapError::new(format!(
+                "Destination too short: {} < {}",
+                dst.len(),
+                dst_size
+            )));
+        }
+
+        if width == 0 || height == 0 {
+            return Ok(());
+        }
+
+        let dst = &mut dst[..dst_size];
+
+        #[cfg(feature = "rayon")]
+        dst.par_chunks_mut(dst_stride)
+            .zip(src.par_chunks(stride))
+            .with_min_len(16)
+            .for_each(|(dst, src)| self.convert_row(src, &mut dst[..dst_row_size]));
+
+        #[cfg(not(feature = "rayon"))]
+        dst.chunks_mut(dst_stride)
+            .zip(src.chunks(stride))
+            .for_each(|(dst, src)| self.convert_row(src, &mut dst[..dst_row_size]));
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use xcb::Xid;
+
+    fn layout(
+        bits_per_pixel: u32,
+        byte_order: ImageOrder,
+        [red_mask, green_mask, blue_mask, alpha_mask]: [u32; 4],
+    ) -> PixelLayout {
+        PixelLayout {
+            bits_per_pixel,
+            scanline_pad: 32,
+            byte_order,
+            visual_class: VisualClass::TrueColor,
+            colormap: Colormap::none(),
+            colormap_entries: 0,
+            red_mask,
+            green_mask,
+            blue_mask,
+            alpha_mask,
+        }
+    }
+
+    /// 按掩码把 8 位颜色打包成像素，再按字节序写入
+    fn encode(layout: &PixelLayout, width: u32, pixels: &[[u8; 4]]) -> Vec<u8> {
+        let stride = layout.stride(width) as usize;
+        let bytes_per_pixel = (layout.bits_per_pixel / 8) as usize;
+        let height = pixels.len() / width as usize;
+        // 填充字节设置为非零，确保转换时跳过
+        let mut data = vec![0xa5; stride * height];
+
+        let pack = |value: u8, mask: u32| -> u32 {
+            if mask == 0 {
+                return 0;
+            }
+            let shift = mask.trailing_zeros();
+            let bits = (mask >> shift).trailing_ones();
+            ((value as u32 * ((1 << bits) - 1) + 127) / 255) << shift
+        };
+
+        for (index, rgba) in pixels.iter().enumerate() {
+            let pixel = pack(rgba[0], layout.red_mask)
+                | pack(rgba[1], layout.green_mask)
+                | pack(rgba[2], layout.blue_mask)
+                | pack(rgba[3], layout.alpha_mask);
+
+            let bytes = match layout.byte_order {
+                ImageOrder::LsbFirst => pixel.to_le_bytes()[..bytes_per_pixel].to_vec(),
+                ImageOrder::MsbFirst => pixel.to_be_bytes()[4 - bytes_per_pixel..].to_vec(),
+            };
+
+            let (x, y) = (index % width as usize, index / width as usize);
+            let offset = y * stride + x * bytes_per_pixel;
+            data[offset..offset + bytes_per_pixel].copy_from_slice(&bytes);
+        }
+
+        data
+    }
+
+    fn convert(layout: PixelLayout, width: u32, src: &[u8], height: u32) -> Vec<u8> {
+        let mut dst = vec![0; (width * height * 4) as usize];
+        PixelConverter::from_masks(layout)
+            .unwrap()
+            .convert(src, width, height, &mut dst, width * 4)
+            .unwrap();
+
+        dst
+    }
+
+    /// 各个通道的极值与中间值，宽度为 3 以产生行尾填充
+    fn sample_pixels() -> Vec<[u8; 4]> {
+        vec![
+            [255, 0, 0, 255],
+            [0, 255, 0, 255],
+            [0, 0, 255, 255],
+            [255, 255, 255, 255],
+            [0, 0, 0, 255],
+            [128, 64, 192, 255],
+        ]
+    }
+
+    /// 8 位颜色量化到 bits 位后再扩展回 8 位
+    fn quantize(value: u8, bits: u32) -> u8 {
+        let max = (1 << bits) - 1;
+        let value = (value as u32 * max + 127) / 255;
+        ((value * 255 + max / 2) / max) as u8
+    }
+
+    fn check(layout: PixelLayout, bits: [u32; 3]) {
+        let pixels = sample_pixels();
+        let src = encode(&layout, 3, &pixels);
+        let dst = convert(layout, 3, &src, 2);
+
+        let expected: Vec<u8> = pixels
+            .iter()
+            .flat_map(|rgba| {
+                [
+                    quantize(rgba[0], bits[0]),
+                    quantize(rgba[1], bits[1]),
+                    quantize(rgba[2], bits[2]),
+                    255,
+                ]
+            })
+            .collect();
+
+        assert_eq!(dst, expected, "{:?}", layout);
+    }
+
+    const RGB565: [u32; 4] = [0xf800, 0x07e0, 0x001f, 0];
+    const RGB888: [u32; 4] = [0xff0000, 0x00ff00, 0x0000ff, 0];
+    const RGB101010: [u32; 4] = [0x3ff00000, 0x000ffc00, 0x000003ff, 0];
+
+    #[test]
+    fn depth_8_true_color() {
+        for byte_order in [ImageOrder::LsbFirst, ImageOrder::MsbFirst] {
+            check(layout(8, byte_order, [0xe0, 0x1c, 0x03, 0]), [3, 3, 2]);
+        }
+    }
+
+    #[test]
+    fn depth_15() {
+        for byte_order in [ImageOrder::LsbFirst, ImageOrder::MsbFirst] {
+            check(
+                layout(16, byte_order, [0x7c00, 0x03e0, 0x001f, 0]),
+                [5, 5, 5],
+            );
+        }
+    }
+
+    #[test]
+    fn depth_16() {
+        for byte_order in [ImageOrder::LsbFirst, ImageOrder::MsbFirst] {
+            check(layout(16, byte_order, RGB565), [5, 6, 5]);
+        }
+    }
+
+    #[test]
+    fn depth_24_packed() {
+        for byte_order in [ImageOrder::LsbFirst, ImageOrder::MsbFirst] {
+            check(layout(24, byte_order, RGB888), [8, 8, 8]);
+        }
+    }
+
+    #[test]
+    fn depth_24() {
+        for byte_order in [ImageOrder::LsbFirst, ImageOrder::MsbFirst] {
+            check(layout(32, byte_order, RGB888), [8, 8, 8]);
+        }
+    }
+
+    #[test]
+    fn depth_24_bgr_masks() {
+        for byte_order in [ImageOrder::LsbFirst, ImageOrder::MsbFirst] {
+            check(
+                layout(32, byte_order, [0x0000ff, 0x00ff00, 0xff0000, 0]),
+                [8, 8, 8],
+            );
+        }
+    }
+
+    #[test]
+    fn depth_30() {
+        for byte_order in [ImageOrder::LsbFirst, ImageOrder::MsbFirst] {
+            check(layout(32, byte_order, RGB101010), [10, 10, 10]);
+        }
+    }
+
+    #[test]
+    fn depth_30_keeps_high_bits() {
+        let layout = layout(32, ImageOrder::LsbFirst, RGB101010);
+        // 10 位的 0x200 缩放到 8 位是 128
+        let src = (0x200u32 << 20 | 0x3ff << 10 | 0x001).to_le_bytes();
+
+        assert_eq!(convert(layout, 1, &src, 1), [128, 255, 0, 255]);
+    }
+
+    #[test]
+    fn depth_32_alpha() {
+        for byte_order in [ImageOrder::LsbFirst, ImageOrder::MsbFirst] {
+            let layout = layout(32, byte_order, [0xff0000, 0x00ff00, 0x0000ff, 0xff000000]);
+            let pixels = [[10, 20, 30, 0], [40, 50, 60, 128], [70, 80, 90, 255]];
+            let src = encode(&layout, 3, &pixels);
+
+            assert_eq!(convert(layout, 3, &src, 1), pixels.concat());
+        }
+    }
+
+    #[test]
+    fn ignores_undefined_bits() {
+        // 深度 24 的最高字节内容不确定，不能当作 alpha
+        let layout = layout(32, ImageOrder::LsbFirst, RGB888);
+        let src = [0x30, 0x20, 0x10, 0x00, 0x30, 0x20, 0x10, 0x7f];
+
+        assert_eq!(
+            convert(layout, 2, &src, 1),
+            [0x10, 0x20, 0x30, 255, 0x10, 0x20, 0x30, 255]
+        );
+    }
+
+    #[test]
+    fn many_rows() {
+        // 行数足够多时启用 rayon 也会拆分为多个任务
+        let layout = layout(16, ImageOrder::LsbFirst, RGB565);
+        let pixels: Vec<[u8; 4]> = (0..5 * 100)
+            .map(|index| [(index % 256) as u8, (index / 2 % 256) as u8, 255, 255])
+            .collect();
+        let src = encode(&layout, 5, &pixels);
+        let dst = convert(layout, 5, &src, 100);
+
+        for (rgba, expected) in dst.chunks_exact(4).zip(&pixels) {
+            assert_eq!(rgba[0], quantize(expected[0], 5));
+            assert_eq!(rgba[1], quantize(expected[1], 6));
+            assert_eq!(rgba[2], quantize(expected[2], 5));
+        }
+    }
+
+    #[test]
+    fn short_buffer() {
+        let layout = layout(32, ImageOrder::LsbFirst, RGB888);
+        let mut dst = vec![0; 16];
+        let result = PixelConverter::from_masks(layout)
+            .unwrap()
+            .convert(&[0; 15], 2, 2, &mut dst, 8);
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn padded_destination() {
+        let layout = layout(32, ImageOrder::LsbFirst, RGB888);
+        let src = [
+            0x30, 0x20, 0x10, 0, 0x60, 0x50, 0x40, 0, //
+            0x90, 0x80, 0x70, 0, 0xc0, 0xb0, 0xa0, 0,
+        ];
+        // 写入 4x3 图像中 (1, 1) 开始的 2x2 区域，其它像素保持不变
+        let mut dst = [0xee; 48];
+        PixelConverter::from_masks(layout)
+            .unwrap()
+            .convert(&src, 2, 2, &mut dst[20..], 16)
+            .unwrap();
+
+        assert_eq!(dst[..20], [0xee; 20]);
+        assert_eq!(dst[20..28], [0x10, 0x20, 0x30, 255, 0x40, 0x50, 0x60, 255]);
+        assert_eq!(dst[28..36], [0xee; 8]);
+        assert_eq!(dst[36..44], [0x70, 0x80, 0x90, 255, 0xa0, 0xb0, 0xc0, 255]);
+        assert_eq!(dst[44..], [0xee; 4]);
+
+        let result =
+            PixelConverter::from_masks(layout)
+                .unwrap()
+                .convert(&src, 2, 2, &mut dst[20..], 4);
+        assert!(result.is_err());
+    }
+
+    fn palette_layout(visual_class: VisualClass, byte_order: ImageOrder) -> PixelLayout {
+        PixelLayout {
+            visual_class,
+            colormap_entries: 4,
+            ..layout(8, byte_order, [0; 4])
+        }
+    }
+
+    #[test]
+    fn depth_8_palette() {
+        let palette = vec![
+            [0, 0, 0, 255],
+            [255, 0, 0, 255],
+            [0, 128, 255, 255],
+            [10, 20, 30, 255],
+        ];
+
+        for visual_class in [
+            VisualClass::PseudoColor,
+            VisualClass::StaticColor,
+            VisualClass::GrayScale,
+        ] {
+            for byte_order in [ImageOrder::LsbFirst, ImageOrder::MsbFirst] {
+                let layout = palette_layout(visual_class, byte_order);
+                // 宽度 3 的每行填充到 4 字节，200 超出 colormap 范围
+                let src = [1, 2, 3, 0xa5, 0, 200, 1, 0xa5];
+                let mut dst = vec![0; 24];
+                PixelConverter::from_palette(layout, palette.clone())
+                    .unwrap()
+                    .convert(&src, 3, 2, &mut dst, 12)
+                    .unwrap();
+
+                let expected = [
+                    palette[1],
+                    palette[2],
+                    palette[3],
+                    palette[0],
+                    [0, 0, 0, 255],
+                    palette[1],
+                ];
+                assert_eq!(dst, expected.concat(), "{:?}", layout);
+            }
+        }
+    }
+
+    #[test]
+    fn true_color_needs_masks() {
+        let layout = layout(8, ImageOrder::LsbFirst, [0; 4]);
+
+        assert!(PixelConverter::from_masks(layout).is_err());
+    }
+
+    #[test]
+    fn pixel_formats() {
+        let pixel_format = |bits_per_pixel, byte_order, masks| {
+            layout(bits_per_pixel, byte_order, masks).pixel_format()
+        };
+
+        assert_eq!(
+            pixel_format(16, ImageOrder::LsbFirst, RGB565),
+            Some(PixelFormat::Rgb565)
+        );
+        assert_eq!(
+            pixel_format(32, ImageOrder::LsbFirst, RGB888),
+            Some(PixelFormat::Bgrx8)
+        );
+        assert_eq!(
+            pixel_format(
+                32,
+                ImageOrder::LsbFirst,
+                [0xff0000, 0xff00, 0xff, 0xff000000]
+            ),
+            Some(PixelFormat::Bgra8)
+        );
+        assert_eq!(
+            pixel_format(32, ImageOrder::LsbFirst, RGB101010),
+            Some(PixelFormat::Rgb10A2)
+        );
+        assert_eq!(pixel_format(32, ImageOrder::MsbFirst, RGB888), None);
+        assert_eq!(pixel_format(24, ImageOrder::LsbFirst, RGB888), None);
+        assert_eq!(
+            palette_layout(VisualClass::PseudoColor, ImageOrder::LsbFirst).pixel_format(),
+            None
+        );
+    }
+}
@@ -5,33 +5,36 @@ use core_graphics::{
 };
 use image::RgbaImage;
 
-use crate::error::{XCapError, XCapResult};
+use crate::{
+    error::{XCapError, XCapResult},
+    frame::{Frame, PixelFormat},
+};
 
-pub fn capture(
+pub fn capture_frame(
     cg_rect: CGRect,
     list_option: CGWindowListOption,
     window_id: CGWindowID,
-) -> XCapResult<RgbaImage> {
+) -> XCapResult<Frame> {
     let cg_image = create_image(cg_rect, list_option, window_id, kCGWindowImageDefault)
         .ok_or_else(|| XCapError::new(format!("Capture failed {} {:?}", window_id, cg_rect)))?;
 
-    let width = cg_image.width();
-    let height = cg_image.height();
-    let bytes = Vec::from(cg_image.data().bytes());
-
     // Some platforms e.g. MacOS can have extra bytes at the end of each row.
     // See
     // https://github.com/nashaofu/xcap/issues/29
     // https://github.com/nashaofu/xcap/issues/38
-    let mut buffer = Vec::with_capacity(width * height * 4);
-    for row in bytes.chunks_exact(cg_image.bytes_per_row()) {
-        buffer.extend_from_slice(&row[..width * 4]);
-    }
-
-    for bgra in buffer.chunks_exact_mut(4) {
-        bgra.swap(0, 2);
-    }
+    Frame::from_raw(
+        cg_image.width() as u32,
+        cg_image.height() as u32,
+        cg_image.bytes_per_row() as u32,
+        PixelFormat::Bgra8,
+        Vec::from(cg_image.data().bytes()),
+    )
+}
 
-    RgbaImage::from_raw(width as u32, height as u32, buffer)
-        .ok_or_else(|| XCapError::new("RgbaImage::from_raw failed"))
+pub fn capture(
+    cg_rect: CGRect,
+    list_option: CGWindowListOption,
+    window_id: CGWindowID,
+) -> XCapResult<RgbaImage> {
+    capture_frame(cg_rect, list_option, window_id)?.into_rgba_image()
 }
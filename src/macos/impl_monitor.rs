@@ -1,12 +1,17 @@
 use core_graphics::display::{
     kCGNullWindowID, kCGWindowListOptionAll, CGDirectDisplayID, CGDisplay, CGDisplayMode, CGError,
-    CGPoint,
+    CGPoint, CGRect, CGSize,
 };
 use image::RgbaImage;
+use std::sync::mpsc::Receiver;
 
-use crate::error::{XCapError, XCapResult};
+use crate::{
+    error::{XCapError, XCapResult},
+    frame::Frame,
+    video_recorder::VideoRecorder,
+};
 
-use super::capture::capture;
+use super::capture::{capture, capture_frame};
 
 #[derive(Debug, Clone)]
 pub(crate) struct ImplMonitor {
@@ -132,4 +137,37 @@ impl ImplMonitor {
             kCGNullWindowID,
         )
     }
+
+    pub fn capture_region(&self, x: u32, y: u32, width: u32, height: u32) -> XCapResult<RgbaImage> {
+        let cg_rect = self.cg_display.bounds();
+
+        capture(
+            CGRect::new(
+                &CGPoint::new(cg_rect.origin.x + x as f64, cg_rect.origin.y + y as f64),
+                &CGSize::new(width as f64, height as f64),
+            ),
+            kCGWindowListOptionAll,
+            kCGNullWindowID,
+        )
+    }
+
+    pub fn capture_frame(&self) -> XCapResult<Frame> {
+        capture_frame(
+            self.cg_display.bounds(),
+            kCGWindowListOptionAll,
+            kCGNullWindowID,
+        )
+    }
+
+    pub fn capture_image_bgra_data(&self) -> XCapResult<Vec<u8>> {
+        self.capture_frame()?.into_bgra_data()
+    }
+
+    pub fn video_recorder(&self) -> XCapResult<(VideoRecorder, Receiver<XCapResult<Frame>>)> {
+        let cg_rect = self.cg_display.bounds();
+
+        Ok(VideoRecorder::from_capture(move || {
+            capture(cg_rect, kCGWindowListOptionAll, kCGNullWindowID)
+        }))
+    }
 }
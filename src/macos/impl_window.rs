@@ -16,9 +16,12 @@ use core_graphics::{
 use image::RgbaImage;
 use std::ffi::c_void;
 
-use crate::{error::XCapResult, XCapError};
+use crate::{error::XCapResult, frame::Frame, XCapError};
 
-use super::{capture::capture, impl_monitor::ImplMonitor};
+use super::{
+    capture::{capture, capture_frame},
+    impl_monitor::ImplMonitor,
+};
 
 #[derive(Debug, Clone)]
 pub(crate) struct ImplWindow {
@@ -26,6 +29,7 @@ pub(crate) struct ImplWindow {
     pub id: u32,
     pub title: String,
     pub app_name: String,
+    pub process_id: u32,
     pub current_monitor: ImplMonitor,
     pub x: i32,
     pub y: i32,
@@ -128,6 +132,7 @@ impl ImplWindow {
         window_owner_name: String,
     ) -> XCapResult<ImplWindow> {
         let id = get_cf_number_u32_value(window_cf_dictionary_ref, "kCGWindowNumber")?;
+        let process_id = get_cf_number_u32_value(window_cf_dictionary_ref, "kCGWindowOwnerPID")?;
         let cg_rect = get_window_cg_rect(window_cf_dictionary_ref)?;
 
         let primary_monitor = ImplMonitor::new(CGDisplay::main().id)?;
@@ -164,6 +169,7 @@ impl ImplWindow {
             id,
             title: window_name,
             app_name: window_owner_name,
+            process_id,
             current_monitor: current_monitor.clone(),
             x: cg_rect.origin.x as i32,
             y: cg_rect.origin.y as i32,
@@ -257,4 +263,16 @@ impl ImplWindow {
             self.id,
         )
     }
+
+    pub fn capture_frame(&self) -> XCapResult<Frame> {
+        capture_frame(
+            get_window_cg_rect(self.window_cf_dictionary_ref)?,
+            kCGWindowListOptionIncludingWindow,
+            self.id,
+        )
+    }
+
+    pub fn capture_image_bgra_data(&self) -> XCapResult<Vec<u8>> {
+        self.capture_frame()?.into_bgra_data()
+    }
 }
@@ -1,6 +1,17 @@
 use image::RgbaImage;
+use std::sync::mpsc::Receiver;
 
-use crate::{error::XCapResult, platform::impl_monitor::ImplMonitor};
+#[cfg(target_os = "linux")]
+use crate::{
+    capture_options::CaptureOptions, damage_tracker::DamageTracker, frame_buffer::FrameBuffer,
+};
+use crate::{
+    error::{XCapError, XCapResult},
+    frame::Frame,
+    platform::impl_monitor::ImplMonitor,
+    rect::Rect,
+    video_recorder::VideoRecorder,
+};
 
 #[derive(Debug, Clone)]
 pub struct Monitor {
@@ -11,6 +22,22 @@ impl Monitor {
     pub(crate) fn new(impl_monitor: ImplMonitor) -> Monitor {
         Monitor { impl_monitor }
     }
+
+    /// The region must be non-empty and inside the monitor.
+    pub(crate) fn check_region(&self, x: u32, y: u32, width: u32, height: u32) -> XCapResult<()> {
+        if width == 0
+            || height == 0
+            || x.saturating_add(width) > self.width()
+            || y.saturating_add(height) > self.height()
+        {
+            return Err(XCapError::InvalidRegion {
+                region: Rect::new(x as i32, y as i32, width, height),
+                bounds: Rect::new(0, 0, self.width(), self.height()),
+            });
+        }
+
+        Ok(())
+    }
 }
 
 impl Monitor {
@@ -75,12 +102,56 @@ impl Monitor {
 
 impl Monitor {
     /// Capture image of the monitor
+    ///
+    /// On X11 all captures share one X connection and MIT-SHM segment, kept open
+    /// after the first capture, see `last_x11_capture_method`.
     pub fn capture_image(&self) -> XCapResult<RgbaImage> {
         self.impl_monitor.capture_image()
     }
 
+    #[cfg(target_os = "linux")]
+    /// Capture image of the monitor, see `CaptureOptions`
+    pub fn capture_image_with_options(&self, options: &CaptureOptions) -> XCapResult<RgbaImage> {
+        self.impl_monitor.capture_image_with_options(options)
+    }
+
+    /// Capture image of a region of the monitor.
+    /// The coordinates are relative to the monitor's top-left corner.
+    pub fn capture_region(&self, x: u32, y: u32, width: u32, height: u32) -> XCapResult<RgbaImage> {
+        self.check_region(x, y, width, height)?;
+
+        self.impl_monitor.capture_region(x, y, width, height)
+    }
+
+    #[cfg(target_os = "linux")]
+    /// Capture the monitor into `frame_buffer`. On X11 the pixels are converted directly
+    /// into the buffer, reusing its allocation when the size has not grown.
+    /// Other backends capture a new image and the buffer takes over its allocation.
+    pub fn capture_into(&self, frame_buffer: &mut FrameBuffer) -> XCapResult<()> {
+        self.impl_monitor.capture_into(frame_buffer)
+    }
+
+    /// Capture the monitor in the pixel layout the platform produces, without converting it.
+    /// Call `Frame::to_rgba_image` when an `RgbaImage` is needed.
+    pub fn capture_frame(&self) -> XCapResult<Frame> {
+        self.impl_monitor.capture_frame()
+    }
+
     /// Capture image of the monitor
     pub fn capture_image_bgra_data(&self) -> XCapResult<Vec<u8>> {
         self.impl_monitor.capture_image_bgra_data()
     }
+
+    /// Create a video recorder of the monitor.
+    /// Frames are sent to the returned receiver after `VideoRecorder::start()`.
+    /// When a capture fails the error is the last item, the channel then closes.
+    pub fn video_recorder(&self) -> XCapResult<(VideoRecorder, Receiver<XCapResult<Frame>>)> {
+        self.impl_monitor.video_recorder()
+    }
+
+    #[cfg(target_os = "linux")]
+    /// Create a tracker of the areas of the monitor that change between captures.
+    pub fn damage_tracker(&self) -> XCapResult<DamageTracker> {
+        DamageTracker::from_monitor(self)
+    }
 }
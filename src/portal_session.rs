@@ -0,0 +1,40 @@
+use crate::{error::XCapResult, platform::impl_portal_session::ImplPortalSession};
+
+/// The xdg-desktop-portal ScreenCast session used by the PipeWire backend.
+///
+/// Store `restore_token()` and pass it to `PortalSession::start` on the next run,
+/// the portal then restores the permission without asking the user again.
+/// Captures keep using the session after this handle is dropped, call `close` to end it.
+#[derive(Debug, Clone)]
+pub struct PortalSession {
+    pub(crate) impl_portal_session: ImplPortalSession,
+}
+
+impl PortalSession {
+    pub(crate) fn new(impl_portal_session: ImplPortalSession) -> PortalSession {
+        PortalSession {
+            impl_portal_session,
+        }
+    }
+}
+
+impl PortalSession {
+    /// Start a session that persists the permission until the user revokes it,
+    /// replacing the current one. With a valid `restore_token` no dialog is shown.
+    pub fn start(restore_token: Option<&str>) -> XCapResult<PortalSession> {
+        let impl_portal_session = ImplPortalSession::start(restore_token)?;
+
+        Ok(PortalSession::new(impl_portal_session))
+    }
+
+    /// The token to pass to `start` next time. Tokens are single use and change
+    /// whenever the session is restarted, so fetch it again before storing it.
+    pub fn restore_token(&self) -> XCapResult<Option<String>> {
+        self.impl_portal_session.restore_token()
+    }
+
+    /// End the session, the next capture starts a new one.
+    pub fn close(self) -> XCapResult<()> {
+        self.impl_portal_session.close()
+    }
+}
@@ -0,0 +1,60 @@
+use std::path::{Path, PathBuf};
+use sysinfo::{Pid, ProcessRefreshKind, System, UpdateKind};
+
+use crate::error::{XCapError, XCapResult};
+
+/// Information about the process that owns a window.
+#[derive(Debug, Clone)]
+pub struct Process {
+    pid: u32,
+    name: String,
+    exe: Option<PathBuf>,
+    cmd: Vec<String>,
+}
+
+impl Process {
+    pub(crate) fn from_pid(pid: u32) -> XCapResult<Process> {
+        if pid == 0 {
+            return Err(XCapError::new("Process id is unknown"));
+        }
+
+        let pid = Pid::from_u32(pid);
+        let mut system = System::new();
+        system.refresh_process_specifics(
+            pid,
+            ProcessRefreshKind::new()
+                .with_exe(UpdateKind::Always)
+                .with_cmd(UpdateKind::Always),
+        );
+
+        let process = system
+            .process(pid)
+            .ok_or_else(|| XCapError::new(format!("Process {} not found", pid)))?;
+
+        Ok(Process {
+            pid: pid.as_u32(),
+            name: process.name().to_string(),
+            exe: process.exe().map(Path::to_path_buf),
+            cmd: process.cmd().to_vec(),
+        })
+    }
+}
+
+impl Process {
+    /// The process id
+    pub fn pid(&self) -> u32 {
+        self.pid
+    }
+    /// The process name
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+    /// The path of the process executable, if it can be read
+    pub fn exe(&self) -> Option<&Path> {
+        self.exe.as_deref()
+    }
+    /// The command line the process was started with
+    pub fn cmd(&self) -> &[String] {
+        &self.cmd
+    }
+}
@@ -0,0 +1,205 @@
+/// A rectangle in desktop coordinates.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Rect {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Rect {
+    pub fn new(x: i32, y: i32, width: u32, height: u32) -> Rect {
+        Rect {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    fn right(&self) -> i32 {
+        self.x + self.width as i32
+    }
+
+    fn bottom(&self) -> i32 {
+        self.y + self.height as i32
+    }
+
+    /// Whether the rectangle has no area.
+    pub fn is_empty(&self) -> bool {
+        self.width == 0 || self.height == 0
+    }
+
+    /// The area shared by both rectangles, `None` if they do not overlap.
+    pub fn intersection(&self, other: &Rect) -> Option<Rect> {
+        let left = self.x.max(other.x);
+        let top = self.y.max(other.y);
+        let right = self.right().min(other.right());
+        let bottom = self.bottom().min(other.bottom());
+
+        if right <= left || bottom <= top {
+            return None;
+        }
+
+        Some(Rect::new(
+            left,
+            top,
+            (right - left) as u32,
+            (bottom - top) as u32,
+        ))
+    }
+
+    /// The parts of this rectangle not covered by `other`, as at most 4 disjoint rectangles.
+    pub fn subtract(&self, other: &Rect) -> Vec<Rect> {
+        let Some(overlap) = self.intersection(other) else {
+            return vec![*self];
+        };
+
+        // 上下两条占满整个宽度，左右两条只取重叠部分的高度
+        [
+            Rect::new(self.x, self.y, self.width, (overlap.y - self.y) as u32),
+            Rect::new(
+                self.x,
+                overlap.bottom(),
+                self.width,
+                (self.bottom() - overlap.bottom()) as u32,
+            ),
+            Rect::new(
+                self.x,
+                overlap.y,
+                (overlap.x - self.x) as u32,
+                overlap.height,
+            ),
+            Rect::new(
+                overlap.right(),
+                overlap.y,
+                (self.right() - overlap.right()) as u32,
+                overlap.height,
+            ),
+        ]
+        .into_iter()
+        .filter(|rect| !rect.is_empty())
+        .collect()
+    }
+}
+
+/// The size of the window decorations on each side of the client area.
+/// Positive values are decorations drawn around the client area by the window manager,
+/// negative values are invisible client-side shadows inside the client area.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct FrameExtents {
+    pub left: i32,
+    pub right: i32,
+    pub top: i32,
+    pub bottom: i32,
+}
+
+impl FrameExtents {
+    /// The rectangle covered by the window frame around the client area `client`,
+    /// negative values shrink it to the part that is drawn.
+    #[cfg(target_os = "linux")]
+    pub(crate) fn frame_rect(&self, client: &Rect) -> Rect {
+        Rect::new(
+            client.x - self.left,
+            client.y - self.top,
+            (client.width as i32 + self.left + self.right).max(0) as u32,
+            (client.height as i32 + self.top + self.bottom).max(0) as u32,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn intersection() {
+        let rect = Rect::new(0, 0, 100, 50);
+
+        // 包含
+        assert_eq!(
+            rect.intersection(&Rect::new(10, 10, 20, 20)),
+            Some(Rect::new(10, 10, 20, 20))
+        );
+        assert_eq!(
+            Rect::new(10, 10, 20, 20).intersection(&rect),
+            Some(Rect::new(10, 10, 20, 20))
+        );
+        assert_eq!(rect.intersection(&rect), Some(rect));
+        // 部分重叠
+        assert_eq!(
+            rect.intersection(&Rect::new(-10, 40, 30, 30)),
+            Some(Rect::new(0, 40, 20, 10))
+        );
+        // 边缘相接没有交集
+        assert_eq!(rect.intersection(&Rect::new(100, 0, 10, 50)), None);
+        assert_eq!(rect.intersection(&Rect::new(0, -10, 100, 10)), None);
+        // 不相交
+        assert_eq!(rect.intersection(&Rect::new(200, 200, 10, 10)), None);
+        // 面积为 0
+        assert_eq!(rect.intersection(&Rect::new(10, 10, 0, 10)), None);
+        assert_eq!(Rect::new(10, 10, 10, 0).intersection(&rect), None);
+    }
+
+    #[test]
+    fn subtract() {
+        let rect = Rect::new(0, 0, 100, 50);
+
+        // 不相交、边缘相接或面积为 0 时保持不变
+        assert_eq!(rect.subtract(&Rect::new(200, 200, 10, 10)), vec![rect]);
+        assert_eq!(rect.subtract(&Rect::new(100, 0, 10, 50)), vec![rect]);
+        assert_eq!(rect.subtract(&Rect::new(10, 10, 0, 0)), vec![rect]);
+        // 完全覆盖
+        assert_eq!(rect.subtract(&Rect::new(-10, -10, 200, 200)), vec![]);
+        assert_eq!(rect.subtract(&rect), vec![]);
+        // 覆盖左半边
+        assert_eq!(
+            rect.subtract(&Rect::new(-10, -10, 60, 100)),
+            vec![Rect::new(50, 0, 50, 50)]
+        );
+        // 覆盖右下角
+        assert_eq!(
+            rect.subtract(&Rect::new(60, 30, 100, 100)),
+            vec![Rect::new(0, 0, 100, 30), Rect::new(0, 30, 60, 20)]
+        );
+        // 在中间挖一个洞
+        assert_eq!(
+            rect.subtract(&Rect::new(10, 10, 20, 20)),
+            vec![
+                Rect::new(0, 0, 100, 10),
+                Rect::new(0, 30, 100, 20),
+                Rect::new(0, 10, 10, 20),
+                Rect::new(30, 10, 70, 20),
+            ]
+        );
+    }
+
+    #[test]
+    fn frame_rect() {
+        let client = Rect::new(100, 100, 200, 100);
+
+        assert_eq!(FrameExtents::default().frame_rect(&client), client);
+        // 窗口管理器绘制的标题栏和边框
+        let frame_extents = FrameExtents {
+            left: 2,
+            right: 2,
+            top: 30,
+            bottom: 2,
+        };
+        assert_eq!(
+            frame_extents.frame_rect(&client),
+            Rect::new(98, 70, 204, 132)
+        );
+        // 客户端绘制的透明阴影
+        let frame_extents = FrameExtents {
+            left: -10,
+            right: -10,
+            top: -5,
+            bottom: -15,
+        };
+        assert_eq!(
+            frame_extents.frame_rect(&client),
+            Rect::new(110, 105, 180, 80)
+        );
+    }
+}
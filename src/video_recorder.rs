@@ -0,0 +1,198 @@
+use image::RgbaImage;
+use std::{
+    sync::{
+        atomic::{AtomicBool, AtomicU32, Ordering},
+        mpsc::{self, Receiver},
+        Arc, Condvar, Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    error::{XCapError, XCapResult},
+    frame::Frame,
+};
+
+/// Events sent while recording a window.
+#[derive(Debug)]
+pub enum WindowRecorderEvent {
+    /// A captured frame of the window.
+    Frame(Frame),
+    /// The window was resized, following frames have the new size.
+    Resized { width: u32, height: u32 },
+    /// The window is gone, or it failed to be captured for many frames in a row.
+    /// This is the last event of the stream.
+    Closed(XCapError),
+}
+
+#[derive(Debug)]
+struct RecorderState {
+    is_running: Mutex<bool>,
+    condvar: Condvar,
+    is_closed: AtomicBool,
+    is_finished: AtomicBool,
+    fps: AtomicU32,
+}
+
+/// Records frames on a background thread between `start()` and `stop()`.
+#[derive(Debug)]
+pub struct VideoRecorder {
+    state: Arc<RecorderState>,
+}
+
+impl VideoRecorder {
+    pub const DEFAULT_FPS: u32 = 30;
+
+    /// Spawn the recording thread. `tick` is called once per frame period while
+    /// the recorder is running, and ends the recording when it returns false.
+    pub(crate) fn spawn<F>(mut tick: F) -> VideoRecorder
+    where
+        F: FnMut(Duration) -> bool + Send + 'static,
+    {
+        let state = Arc::new(RecorderState {
+            is_running: Mutex::new(false),
+            condvar: Condvar::new(),
+            is_closed: AtomicBool::new(false),
+            is_finished: AtomicBool::new(false),
+            fps: AtomicU32::new(VideoRecorder::DEFAULT_FPS),
+        });
+
+        let thread_state = state.clone();
+        thread::spawn(move || {
+            let state = thread_state;
+
+            'recording: loop {
+                {
+                    let Ok(mut is_running) = state.is_running.lock() else {
+                        break;
+                    };
+                    while !*is_running && !state.is_closed.load(Ordering::Acquire) {
+                        is_running = match state.condvar.wait(is_running) {
+                            Ok(is_running) => is_running,
+                            Err(_) => break 'recording,
+                        };
+                    }
+                }
+
+                let started_at = Instant::now();
+                loop {
+                    if state.is_closed.load(Ordering::Acquire) {
+                        break 'recording;
+                    }
+                    if !state.is_running.lock().map(|r| *r).unwrap_or(false) {
+                        break;
+                    }
+
+                    let frame_started_at = Instant::now();
+                    if !tick(started_at.elapsed()) {
+                        break 'recording;
+                    }
+
+                    let fps = state.fps.load(Ordering::Relaxed).max(1);
+                    let period = Duration::from_secs(1) / fps;
+                    thread::sleep(period.saturating_sub(frame_started_at.elapsed()));
+                }
+            }
+
+            state.is_finished.store(true, Ordering::Release);
+        });
+
+        VideoRecorder { state }
+    }
+
+    /// Create a recorder that sends every captured image as a `Frame`.
+    /// The recording ends when the receiver is dropped, or when `capture` fails,
+    /// after sending the error as the last item.
+    pub(crate) fn from_capture<F>(mut capture: F) -> (VideoRecorder, Receiver<XCapResult<Frame>>)
+    where
+        F: FnMut() -> XCapResult<RgbaImage> + Send + 'static,
+    {
+        let (sender, receiver) = mpsc::channel();
+
+        let video_recorder = VideoRecorder::spawn(move |timestamp| match capture() {
+            Ok(rgba_image) => sender.send(Ok(Frame::new(timestamp, rgba_image))).is_ok(),
+            Err(err) => {
+                log::error!("VideoRecorder capture failed: {}", err);
+                let _ = sender.send(Err(err));
+                false
+            }
+        });
+
+        (video_recorder, receiver)
+    }
+
+    fn set_running(&self, running: bool) -> XCapResult<()> {
+        if self.state.is_finished.load(Ordering::Acquire) {
+            return Err(XCapError::new("Video recorder finished"));
+        }
+
+        let mut is_running = self
+            .state
+            .is_running
+            .lock()
+            .map_err(|_| XCapError::new("Get running lock failed"))?;
+        *is_running = running;
+        self.state.condvar.notify_all();
+
+        Ok(())
+    }
+}
+
+impl VideoRecorder {
+    /// Start or resume recording.
+    pub fn start(&self) -> XCapResult<()> {
+        self.set_running(true)
+    }
+    /// Pause recording, it can be resumed with `start()`.
+    pub fn stop(&self) -> XCapResult<()> {
+        self.set_running(false)
+    }
+    /// Whether the recorder is capturing frames.
+    pub fn is_running(&self) -> bool {
+        !self.state.is_finished.load(Ordering::Acquire)
+            && self.state.is_running.lock().map(|r| *r).unwrap_or(false)
+    }
+    /// The target frames per second.
+    pub fn fps(&self) -> u32 {
+        self.state.fps.load(Ordering::Relaxed)
+    }
+    /// Set the target frames per second, takes effect from the next frame.
+    pub fn set_fps(&self, fps: u32) {
+        self.state.fps.store(fps.max(1), Ordering::Relaxed);
+    }
+}
+
+impl Drop for VideoRecorder {
+    fn drop(&mut self) {
+        self.state.is_closed.store(true, Ordering::Release);
+        // 持有锁再通知，避免录制线程错过唤醒
+        let _guard = self.state.is_running.lock();
+        self.state.condvar.notify_all();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn capture_error_is_sent() {
+        let mut count = 0;
+        let (video_recorder, receiver) = VideoRecorder::from_capture(move || {
+            count += 1;
+            if count > 2 {
+                return Err(XCapError::MonitorGone(1));
+            }
+            Ok(RgbaImage::new(2, 2))
+        });
+        video_recorder.set_fps(1000);
+        video_recorder.start().unwrap();
+
+        let items: Vec<XCapResult<Frame>> = receiver.iter().collect();
+        assert_eq!(items.len(), 3);
+        assert!(items[..2].iter().all(|item| item.is_ok()));
+        assert!(matches!(items[2], Err(XCapError::MonitorGone(1))));
+        assert!(!video_recorder.is_running());
+    }
+}
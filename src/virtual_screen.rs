@@ -0,0 +1,127 @@
+use image::{imageops, RgbaImage};
+
+use crate::{
+    error::{XCapError, XCapResult},
+    Monitor,
+};
+
+/// The bounding box of all monitors, treated as one desktop.
+#[derive(Debug, Clone)]
+pub struct VirtualScreen {
+    monitors: Vec<Monitor>,
+    x: i32,
+    y: i32,
+    width: u32,
+    height: u32,
+}
+
+impl VirtualScreen {
+    pub fn new() -> XCapResult<VirtualScreen> {
+        VirtualScreen::from_monitors(Monitor::all()?)
+    }
+
+    pub fn from_monitors(monitors: Vec<Monitor>) -> XCapResult<VirtualScreen> {
+        let first = monitors
+            .first()
+            .ok_or_else(|| XCapError::new("Not found monitor"))?;
+
+        let mut left = first.x();
+        let mut top = first.y();
+        let mut right = first.x() + first.width() as i32;
+        let mut bottom = first.y() + first.height() as i32;
+
+        for monitor in monitors.iter() {
+            left = left.min(monitor.x());
+            top = top.min(monitor.y());
+            right = right.max(monitor.x() + monitor.width() as i32);
+            bottom = bottom.max(monitor.y() + monitor.height() as i32);
+        }
+
+        Ok(VirtualScreen {
+            monitors,
+            x: left,
+            y: top,
+            width: (right - left) as u32,
+            height: (bottom - top) as u32,
+        })
+    }
+}
+
+impl VirtualScreen {
+    /// The monitors that make up the virtual screen.
+    pub fn monitors(&self) -> &[Monitor] {
+        &self.monitors
+    }
+    /// The virtual screen x coordinate.
+    pub fn x(&self) -> i32 {
+        self.x
+    }
+    /// The virtual screen y coordinate.
+    pub fn y(&self) -> i32 {
+        self.y
+    }
+    /// The virtual screen width, in monitor coordinates.
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+    /// The virtual screen height, in monitor coordinates.
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+}
+
+impl VirtualScreen {
+    /// Capture image of the whole desktop.
+    /// Areas not covered by any monitor are transparent.
+    #[cfg(not(target_os = "linux"))]
+    pub fn capture_image(&self) -> XCapResult<RgbaImage> {
+        self.stitch_monitor_images()
+    }
+
+    /// Capture image of the whole desktop.
+    /// Areas not covered by any monitor are transparent.
+    #[cfg(target_os = "linux")]
+    pub fn capture_image(&self) -> XCapResult<RgbaImage> {
+        crate::platform::capture::capture_virtual_screen(self)
+    }
+
+    /// Capture every monitor and paste them into one image.
+    /// Monitors whose captured pixel size differs from the others' scale are resized
+    /// so that the whole image uses the largest pixel density found.
+    pub(crate) fn stitch_monitor_images(&self) -> XCapResult<RgbaImage> {
+        let mut monitor_images = Vec::with_capacity(self.monitors.len());
+        let mut scale = 1.0_f32;
+
+        for monitor in self.monitors.iter() {
+            let image = monitor.capture_image()?;
+            scale = scale.max(image.width() as f32 / monitor.width() as f32);
+            monitor_images.push((monitor, image));
+        }
+
+        let mut rgba_image = RgbaImage::new(
+            (self.width as f32 * scale).round() as u32,
+            (self.height as f32 * scale).round() as u32,
+        );
+
+        for (monitor, mut image) in monitor_images {
+            let width = (monitor.width() as f32 * scale).round() as u32;
+            let height = (monitor.height() as f32 * scale).round() as u32;
+
+            if image.width() != width || image.height() != height {
+                image = imageops::resize(&image, width, height, imageops::FilterType::Triangle);
+            }
+
+            let x = ((monitor.x() - self.x) as f32 * scale).round() as i64;
+            let y = ((monitor.y() - self.y) as f32 * scale).round() as i64;
+
+            imageops::replace(&mut rgba_image, &image, x, y);
+        }
+
+        Ok(rgba_image)
+    }
+}
+
+/// Capture all monitors as one image, see [`VirtualScreen::capture_image`].
+pub fn capture_all_monitors() -> XCapResult<RgbaImage> {
+    VirtualScreen::new()?.capture_image()
+}
@@ -1,6 +1,15 @@
 use image::RgbaImage;
+#[cfg(target_os = "linux")]
+use std::sync::mpsc::Receiver;
 
-use crate::{error::XCapResult, platform::impl_window::ImplWindow, Monitor};
+#[cfg(target_os = "linux")]
+use crate::{
+    capture_options::WindowCaptureMode,
+    damage_tracker::DamageTracker,
+    video_recorder::{VideoRecorder, WindowRecorderEvent},
+    FrameExtents, Rect,
+};
+use crate::{error::XCapResult, frame::Frame, platform::impl_window::ImplWindow, Monitor, Process};
 
 #[derive(Debug, Clone)]
 pub struct Window {
@@ -14,6 +23,10 @@ impl Window {
 }
 
 impl Window {
+    /// All windows, on Linux ordered from the topmost to the bottommost.
+    /// On Wayland without XWayland windows come from ext-foreign-toplevel-list,
+    /// which has no position, monitor, state, stacking order or focus.
+    /// The getters of these Wayland-only windows return the placeholder values noted on them.
     pub fn all() -> XCapResult<Vec<Window>> {
         let windows = ImplWindow::all()?
             .iter()
@@ -37,21 +50,24 @@ impl Window {
     pub fn title(&self) -> &str {
         &self.impl_window.title
     }
-
-    #[cfg(target_os = "windows")]
-    /// The window process id
+    /// The window process id, 0 if it is unknown
     pub fn process_id(&self) -> u32 {
         self.impl_window.process_id
     }
-    /// The window current monitor
+    /// The process that owns the window
+    pub fn process(&self) -> XCapResult<Process> {
+        Process::from_pid(self.process_id())
+    }
+    /// The window current monitor.
+    /// Always the first monitor for Wayland-only windows, see `Window::all`.
     pub fn current_monitor(&self) -> Monitor {
         Monitor::new(self.impl_window.current_monitor.to_owned())
     }
-    /// The window x coordinate.
+    /// The window x coordinate, always 0 for Wayland-only windows.
     pub fn x(&self) -> i32 {
         self.impl_window.x
     }
-    /// The window y coordinate.
+    /// The window y coordinate, always 0 for Wayland-only windows.
     pub fn y(&self) -> i32 {
         self.impl_window.y
     }
@@ -63,14 +79,31 @@ impl Window {
     pub fn height(&self) -> u32 {
         self.impl_window.height
     }
-    /// The window is minimized.
+    /// The window is minimized, always false for Wayland-only windows.
     pub fn is_minimized(&self) -> bool {
         self.impl_window.is_minimized
     }
-    /// The window is maximized.
+    /// The window is maximized, always false for Wayland-only windows.
     pub fn is_maximized(&self) -> bool {
         self.impl_window.is_maximized
     }
+    #[cfg(target_os = "linux")]
+    /// The window stacking position, windows with a higher value are above.
+    /// Always 0 for Wayland-only windows.
+    pub fn z_index(&self) -> i32 {
+        self.impl_window.z_index
+    }
+    #[cfg(target_os = "linux")]
+    /// The window has the input focus, always false for Wayland-only windows.
+    pub fn is_focused(&self) -> bool {
+        self.impl_window.is_focused
+    }
+    #[cfg(target_os = "linux")]
+    /// The window decorations size, see `FrameExtents`.
+    /// Always zero for Wayland-only windows.
+    pub fn frame_extents(&self) -> FrameExtents {
+        self.impl_window.frame_extents
+    }
 }
 
 impl Window {
@@ -81,4 +114,38 @@ impl Window {
     pub fn capture_image_bgra_data(&self) -> XCapResult<Vec<u8>> {
         self.impl_window.capture_image_bgra_data()
     }
+
+    /// Capture the window in the pixel layout the platform produces, without converting it.
+    /// Call `Frame::to_rgba_image` when an `RgbaImage` is needed.
+    pub fn capture_frame(&self) -> XCapResult<Frame> {
+        self.impl_window.capture_frame()
+    }
+
+    #[cfg(target_os = "linux")]
+    /// Capture image of the window with or without its decorations, see `WindowCaptureMode`.
+    pub fn capture_image_with_mode(&self, mode: WindowCaptureMode) -> XCapResult<RgbaImage> {
+        self.impl_window.capture_image_with_mode(mode)
+    }
+
+    #[cfg(target_os = "linux")]
+    /// Create a video recorder of the window.
+    /// Resizes are reported before the first frame with the new size,
+    /// and the stream ends with `WindowRecorderEvent::Closed` when the window is destroyed
+    /// or can no longer be captured.
+    pub fn video_recorder(&self) -> XCapResult<(VideoRecorder, Receiver<WindowRecorderEvent>)> {
+        self.impl_window.video_recorder()
+    }
+
+    #[cfg(target_os = "linux")]
+    /// The parts of the window not covered by the windows above it and their decorations,
+    /// empty when minimized.
+    pub fn visible_region(&self) -> XCapResult<Vec<Rect>> {
+        self.impl_window.visible_region()
+    }
+
+    #[cfg(target_os = "linux")]
+    /// Create a tracker of the areas of the window that change between captures.
+    pub fn damage_tracker(&self) -> XCapResult<DamageTracker> {
+        DamageTracker::from_window(self)
+    }
 }
@@ -16,6 +16,7 @@ use windows::Win32::{
 
 use crate::{
     error::{XCapError, XCapResult},
+    frame::{Frame, PixelFormat},
     platform::utils::get_window_rect,
 };
 
@@ -88,6 +89,30 @@ fn to_rgba_image(
         .ok_or_else(|| XCapError::new("RgbaImage::from_raw failed"))
 }
 
+fn to_frame(
+    box_hdc_mem: BoxHDC,
+    box_h_bitmap: BoxHBITMAP,
+    width: i32,
+    height: i32,
+) -> XCapResult<Frame> {
+    let buffer = get_bgra_image_data(box_hdc_mem, box_h_bitmap, width, height)?;
+
+    // Windows 8 之前 GDI 不写入 alpha 通道
+    let pixel_format = if get_os_major_version() < 8 {
+        PixelFormat::Bgrx8
+    } else {
+        PixelFormat::Bgra8
+    };
+
+    Frame::from_raw(
+        width as u32,
+        height as u32,
+        width as u32 * 4,
+        pixel_format,
+        buffer,
+    )
+}
+
 #[allow(unused)]
 pub fn capture_monitor_bgra_data(x: i32, y: i32, width: i32, height: i32) -> XCapResult<Vec<u8>> {
     unsafe {
@@ -139,6 +164,14 @@ fn inner_capture_monitor(x: i32, y: i32, width: i32, height: i32) -> Result<(Box
     }
 }
 
+#[allow(unused)]
+pub fn capture_monitor_frame(x: i32, y: i32, width: i32, height: i32) -> XCapResult<Frame> {
+    unsafe {
+        let (box_hdc_mem, box_h_bitmap) = inner_capture_monitor(x, y, width, height)?;
+        to_frame(box_hdc_mem, box_h_bitmap, width, height)
+    }
+}
+
 #[allow(unused)]
 pub fn capture_window(hwnd: HWND, scale_factor: f32) -> XCapResult<RgbaImage> {
     unsafe {
@@ -155,6 +188,14 @@ pub fn capture_window_bgra_data(hwnd: HWND, scale_factor: f32) -> XCapResult<Vec
     }
 }
 
+#[allow(unused)]
+pub fn capture_window_frame(hwnd: HWND, scale_factor: f32) -> XCapResult<Frame> {
+    unsafe {
+        let (width, height, box_hdc_mem, box_h_bitmap) = inne_capture_window(hwnd, scale_factor)?;
+        to_frame(box_hdc_mem, box_h_bitmap, width, height)
+    }
+}
+
 unsafe fn inne_capture_window(hwnd: HWND, scale_factor: f32) -> Result<(i32, i32, BoxHDC, BoxHBITMAP), XCapError> {
     let box_hdc_window: BoxHDC = BoxHDC::from(hwnd);
     let rect = get_window_rect(hwnd)?;
@@ -1,5 +1,5 @@
 use image::RgbaImage;
-use std::mem;
+use std::{mem, sync::mpsc::Receiver};
 use windows::{
     core::PCWSTR,
     Win32::{
@@ -16,7 +16,12 @@ use windows::{
 
 use crate::error::{XCapError, XCapResult};
 use crate::platform::capture::capture_monitor_bgra_data;
-use super::{boxed::BoxHDC, capture::capture_monitor, utils::wide_string_to_string};
+use crate::{frame::Frame, video_recorder::VideoRecorder};
+use super::{
+    boxed::BoxHDC,
+    capture::{capture_monitor, capture_monitor_frame},
+    utils::wide_string_to_string,
+};
 
 // A 函数与 W 函数区别
 // https://learn.microsoft.com/zh-cn/windows/win32/learnwin32/working-with-strings
@@ -162,7 +167,28 @@ impl ImplMonitor {
         capture_monitor(self.x, self.y, self.width as i32, self.height as i32)
     }
 
+    pub fn capture_region(&self, x: u32, y: u32, width: u32, height: u32) -> XCapResult<RgbaImage> {
+        capture_monitor(
+            self.x + x as i32,
+            self.y + y as i32,
+            width as i32,
+            height as i32,
+        )
+    }
+
     pub fn capture_image_bgra_data(&self) -> XCapResult<Vec<u8>> {
         capture_monitor_bgra_data(self.x, self.y, self.width as i32, self.height as i32)
     }
+
+    pub fn capture_frame(&self) -> XCapResult<Frame> {
+        capture_monitor_frame(self.x, self.y, self.width as i32, self.height as i32)
+    }
+
+    pub fn video_recorder(&self) -> XCapResult<(VideoRecorder, Receiver<XCapResult<Frame>>)> {
+        let (x, y, width, height) = (self.x, self.y, self.width as i32, self.height as i32);
+
+        Ok(VideoRecorder::from_capture(move || {
+            capture_monitor(x, y, width, height)
+        }))
+    }
 }
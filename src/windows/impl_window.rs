@@ -24,11 +24,12 @@ use windows::{
 
 use crate::{
     error::XCapResult,
+    frame::Frame,
     platform::{boxed::BoxProcessHandle, utils::log_last_error},
 };
 use crate::platform::capture::capture_window_bgra_data;
 use super::{
-    capture::capture_window,
+    capture::{capture_window, capture_window_frame},
     impl_monitor::ImplMonitor,
     utils::{get_window_rect, wide_string_to_string},
 };
@@ -362,4 +363,9 @@ impl ImplWindow {
         // TODO: 在win10之后，不同窗口有不同的dpi，所以可能存在截图不全或者截图有较大空白，实际窗口没有填充满图片
         capture_window_bgra_data(self.hwnd, self.current_monitor.scale_factor)
     }
+
+    pub fn capture_frame(&self) -> XCapResult<Frame> {
+        // TODO: 在win10之后，不同窗口有不同的dpi，所以可能存在截图不全或者截图有较大空白，实际窗口没有填充满图片
+        capture_window_frame(self.hwnd, self.current_monitor.scale_factor)
+    }
 }